@@ -1,77 +1,49 @@
-use std::{path::Path, process::Stdio};
+use std::path::Path;
 
 use anyhow::Result;
-use dmclc5::{minecraft::schemas::VersionList, utils::DownloadAllMessage, LauncherContext, StdioUserInterface};
-use tokio::{process::Command, sync::mpsc};
+use dmclc5::{minecraft::launch::LaunchOptions, utils::{mirror::BMCLAPIMirrorProvider, DownloadEvent, ProgressSink}, LauncherContext, StdioUserInterface};
 
-async fn handle_msg(msg: DownloadAllMessage, count: &mut usize) {
-    match msg {
-        Ok((c, async_fetcher::FetchEvent::ContentLength(len))) => {
-            *count += 1;
-            println!("{} start: {len} ({count})", c.0.display());
-        },
-        Ok((c, async_fetcher::FetchEvent::Fetched)) => {
-            *count -= 1;
-            println!("{} end ({count})", c.0.display());
-        },
-        Ok((_, async_fetcher::FetchEvent::Fetching)) => (),
-        Ok((c, async_fetcher::FetchEvent::Progress(prog))) => {
-            println!("{} fetching: {prog}", c.0.display());
-        },
-        Ok((c, async_fetcher::FetchEvent::Retrying))=> {
-            println!("{} retrying", c.0.display());
-        },
-        Err((c, e)) => {
-            *count -= 1;
-            println!("{} error {e} ({count})", c.0.display());
+struct PrintProgress;
+
+impl ProgressSink for PrintProgress {
+    fn on_event(&self, event: DownloadEvent) {
+        match event {
+            DownloadEvent::Started(phase, p) => {
+                println!("{phase:?}: {} files, {} bytes", p.total_files, p.total_bytes);
+            },
+            DownloadEvent::Progress(p) => {
+                println!("{}/{} files, {}/{} bytes, {} B/s, ETA {:?}", p.finished_files, p.total_files, p.downloaded_bytes, p.total_bytes, p.speed, p.eta);
+            },
+            DownloadEvent::Finished(p) => {
+                println!("Finished: {} files, {} failed", p.finished_files, p.failed_files);
+            },
+            DownloadEvent::FileRetrying(c) => {
+                println!("{} retrying", c.0.display());
+            },
+            DownloadEvent::FileFailed(c, e) => {
+                println!("{} error {e}", c.0.display());
+            },
+            _ => ()
         }
     }
 }
 
 async fn real_main() -> Result<()> {
-    let vers = VersionList::get_list().await?;
     let launcher = {
         let mut launcher = LauncherContext::new(Path::new("./test"), StdioUserInterface).await?;
-        launcher.bmclapi_mirror = Some("bmclapi2.bangbang93.com".into());
+        launcher.mirror_provider = Box::new(BMCLAPIMirrorProvider::official());
         launcher
     };
-    let (tx, mut rx) = mpsc::unbounded_channel();
-    let message_handler = async move {
-        let mut count = 0;
-        while let Some(next) = rx.recv().await {
-            handle_msg(next, &mut count).await;
-        }
-    };
-    let mc = vers.find_by_id("1.20.6").unwrap().install(&launcher, "1.20.6", tx);
-    let mc = tokio::join!(message_handler, mc).1?;
+    let vers = launcher.get_version_list().await?;
+    let mc = vers.find_by_id("1.20.6").unwrap().install(&launcher, "1.20.6", &PrintProgress).await?;
     let account = &mut *launcher.account_types["offline"].new();
     account.login(&launcher).await?;
-    if let Some(c) = &mc.extra_data.before_command {
-        let command: Vec<&str> = c.split(" ").collect();
-        Command::new(command[0])
-            .args(&command[1..])
-            .stdout(Stdio::inherit())
-            .stderr(Stdio::inherit())
-            .stdin(Stdio::null())
-            .current_dir(mc.get_cwd())
-            .spawn()?.wait().await?;
+    let options = LaunchOptions::new();
+    let mut game = mc.launch(account, &options, &PrintProgress).await?;
+    while let Some(line) = game.next_output().await {
+        println!("{}", line.line());
     }
-    let (tx, mut rx) = mpsc::unbounded_channel();
-    let msg_handler = async move {
-        let mut count = 0;
-        while let Some(next) = rx.recv().await {
-            handle_msg(next, &mut count).await;
-        }
-    };
-    let args = mc.launch_args(account, tx);
-    let args = tokio::join!(msg_handler, args).1?;
-    Command::new(mc.extra_data.with_java.as_ref().map_or("java", String::as_str))
-        .args(args)
-        .stdout(Stdio::inherit())
-        .stderr(Stdio::inherit())
-        .stdin(Stdio::null())
-        .current_dir(mc.get_cwd())
-        .spawn()?.wait().await?;
+    println!("Game exited with {}", game.wait().await?);
     Ok(())
 }
 
@@ -1,28 +1,28 @@
 use std::{path::{Path, PathBuf}, str::FromStr};
 
-use dmclc5::{minecraft::schemas::VersionList, utils::{download, BetterPath, DownloadAllMessage}, LauncherContext, StdioUserInterface};
-use tokio::sync::mpsc;
+use dmclc5::{utils::{BetterPath, DownloadEvent, ProgressSink}, LauncherContext, StdioUserInterface};
 
-async fn handle_msg(msg: DownloadAllMessage, count: &mut usize) {
-    match msg {
-        Ok((c, async_fetcher::FetchEvent::ContentLength(len))) => {
-            *count += 1;
-            println!("{} start: {len} ({count})", c.0.display());
-        },
-        Ok((c, async_fetcher::FetchEvent::Fetched)) => {
-            *count -= 1;
-            println!("{} end ({count})", c.0.display());
-        },
-        Ok((_, async_fetcher::FetchEvent::Fetching)) => (),
-        Ok((c, async_fetcher::FetchEvent::Progress(prog))) => {
-            println!("{} fetching: {prog}", c.0.display());
-        },
-        Ok((c, async_fetcher::FetchEvent::Retrying))=> {
-            println!("{} retrying", c.0.display());
-        },
-        Err((c, e)) => {
-            *count -= 1;
-            println!("{} error {e} ({count})", c.0.display());
+struct PrintProgress;
+
+impl ProgressSink for PrintProgress {
+    fn on_event(&self, event: DownloadEvent) {
+        match event {
+            DownloadEvent::Started(phase, p) => {
+                println!("{phase:?}: {} files, {} bytes", p.total_files, p.total_bytes);
+            },
+            DownloadEvent::Progress(p) => {
+                println!("{}/{} files, {}/{} bytes, {} B/s, ETA {:?}", p.finished_files, p.total_files, p.downloaded_bytes, p.total_bytes, p.speed, p.eta);
+            },
+            DownloadEvent::Finished(p) => {
+                println!("Finished: {} files, {} failed", p.finished_files, p.failed_files);
+            },
+            DownloadEvent::FileRetrying(c) => {
+                println!("{} retrying", c.0.display());
+            },
+            DownloadEvent::FileFailed(c, e) => {
+                println!("{} error {e}", c.0.display());
+            },
+            _ => ()
         }
     }
 }
@@ -30,26 +30,11 @@ async fn handle_msg(msg: DownloadAllMessage, count: &mut usize) {
 #[tokio::main]
 async fn main() {
     let launcher: LauncherContext = LauncherContext::new(Path::new("./test"), StdioUserInterface).await.unwrap();
-    let (tx, mut rx) = mpsc::unbounded_channel();
-    let handler = async move {
-        let mut count = 0;
-        while let Some(next) = rx.recv().await {
-            handle_msg(next, &mut count).await;
-        }
-    };
-    let mc = VersionList::get_list().await.unwrap();
-    let mc = mc.find_by_id("1.20.4").unwrap().install(&launcher, "1.20.4-fabric", tx);
-    let mut mc = tokio::join!(handler, mc).1.unwrap();
-    let (tx, mut rx) = mpsc::unbounded_channel();
-    let handler = async move {
-        let mut count = 0;
-        while let Some(next) = rx.recv().await {
-            handle_msg(next, &mut count).await;
-        }
-    };
-    tokio::join!(mc.install_component("fabric", "0.16.0", tx), handler).0.unwrap();
+    let mc = launcher.get_version_list().await.unwrap();
+    let mut mc = mc.find_by_id("1.20.4").unwrap().install(&launcher, "1.20.4-fabric", &PrintProgress).await.unwrap();
+    mc.install_component("fabric", "0.16.0", &PrintProgress).await.unwrap();
     let path = BetterPath(PathBuf::from_str("./test/versions/1.20.4-fabric/mods/entityculling-fabric-1.6.6-mc1.20.4.jar").unwrap());
-    download("https://cdn.modrinth.com/data/NNAgCjsB/versions/cj8nR3eG/entityculling-fabric-1.6.6-mc1.20.4.jar", &path).await.unwrap();
+    launcher.download("https://cdn.modrinth.com/data/NNAgCjsB/versions/cj8nR3eG/entityculling-fabric-1.6.6-mc1.20.4.jar", &path).await.unwrap();
     println!("{:#?}", mc.list_mods().await.unwrap());
     println!("{:#?}", mc.check_mod_dependencies().await.unwrap());
 }
@@ -8,9 +8,8 @@ pub mod fabriclike;
 
 use anyhow::{anyhow, Result};
 use async_trait::async_trait;
-use tokio::sync::mpsc;
 
-use crate::{minecraft::{schemas::VersionJSON, version::{ComponentInfo, MinecraftInstallation}}, utils::DownloadAllMessage, LauncherContext};
+use crate::{minecraft::{schemas::VersionJSON, server::ServerInstallation, version::{ComponentInfo, MinecraftInstallation}}, utils::ProgressSink, LauncherContext};
 
 use super::mods::ModLoader;
 
@@ -24,7 +23,14 @@ pub trait ComponentInstaller: Send + Sync {
     /// Install for a [MinecraftInstallation].
     /// Clients should not call this directly, as it doesn't append [crate::minecraft::version::DMCLCExtraData::components]
     /// Insteadly, clients should call [MinecraftInstallation::install_component].
-    async fn install(&self, mc: &mut MinecraftInstallation, version: &str, download_channel: mpsc::UnboundedSender<DownloadAllMessage>) -> Result<()>;
+    async fn install(&self, mc: &mut MinecraftInstallation, version: &str, progress: &dyn ProgressSink) -> Result<()>;
+
+    /// Install for a [ServerInstallation].
+    /// Clients should call [ServerInstallation::install_component] instead.
+    /// By default, it fails because servers aren't supported.
+    async fn install_server(&self, _: &mut ServerInstallation, version: &str, _: &dyn ProgressSink) -> Result<()> {
+        Err(anyhow!(t!("server.component_unsupported", version = version)))
+    }
 
     /// Find this component in a [MinecraftInstallation]. Returns the version of the component.
     fn find_in_version(&self, v: &VersionJSON) -> Option<String>;
@@ -36,16 +42,28 @@ pub trait ComponentInstaller: Send + Sync {
 }
 
 impl MinecraftInstallation<'_> {
-    /// Install a component.
-    pub async fn install_component(&mut self, component: &str, version: &str, download_channel: mpsc::UnboundedSender<DownloadAllMessage>) -> Result<()> {
+    /// Install a component, and save [MinecraftInstallation::extra_data].
+    pub async fn install_component(&mut self, component: &str, version: &str, progress: &dyn ProgressSink) -> Result<()> {
         if let None = self.extra_data.version {
             return Err(anyhow!(t!("loaders.minecraft_version_unknown")));
         }
-        self.launcher.component_installers[component].install(self, version, download_channel).await?;
+        self.launcher.component_installers[component].install(self, version, progress).await?;
+        self.extra_data.components.push(ComponentInfo {
+            name: component.to_string(),
+            version: version.to_string()
+        });
+        self.save().await
+    }
+}
+
+impl ServerInstallation<'_> {
+    /// Install a component, and save [ServerInstallation::extra_data].
+    pub async fn install_component(&mut self, component: &str, version: &str, progress: &dyn ProgressSink) -> Result<()> {
+        self.launcher.component_installers[component].install_server(self, version, progress).await?;
         self.extra_data.components.push(ComponentInfo {
             name: component.to_string(),
             version: version.to_string()
         });
-        Ok(())
+        self.save().await
     }
 }
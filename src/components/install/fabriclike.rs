@@ -5,9 +5,8 @@ use std::{future::Future, pin::Pin};
 use anyhow::Result;
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
-use tokio::sync::mpsc;
 
-use crate::{components::mods::{fabric::FabricModLoader, quilt::QuiltModLoader, ModLoader}, minecraft::{schemas::VersionJSON, version::MinecraftInstallation}, utils::{download, download_all, maven_coord::ArtifactCoordinate, merge_version_json, DownloadAllMessage}, LauncherContext};
+use crate::{components::mods::{fabric::FabricModLoader, quilt::QuiltModLoader, ModLoader}, minecraft::{schemas::VersionJSON, server::{ServerInstallation, ServerLaunchTarget}, version::MinecraftInstallation}, utils::{download_all, DownloadPhase, maven_coord::ArtifactCoordinate, merge_version_json, ProgressSink}, LauncherContext};
 
 use super::ComponentInstaller;
 
@@ -21,6 +20,7 @@ type GetLoader = fn(String, &'_ LauncherContext) -> Pin<Box<dyn
 pub struct FabricLikeInstaller {
     meta_url: String,
     loader_artifact_name: String,
+    game_jar_property: String,
     #[cfg(feature = "mod_loaders")]
     get_loader: GetLoader,
 }
@@ -44,7 +44,7 @@ async fn fabric_get_loader(version: &str, launcher: &LauncherContext) -> Result<
     let filepath = format!("net/fabricmc/fabric-loader/{version}/fabric-loader-{version}.jar");
     let path = &launcher.root_path / "libraries" / &filepath;
     if !path.0.exists() {
-        download(format!("https://maven.fabricmc.net/{filepath}"), &path).await?;
+        launcher.download(&format!("https://maven.fabricmc.net/{filepath}"), &path).await?;
     }
     let path = &launcher.root_path / "libraries/net/fabricmc/fabric-loader" / version / format!("fabric-loader-{version}.jar");
     loader.builtin_mods = Some(loader.get_mods_in_file(&path).ok().into_iter().flatten().collect());
@@ -66,7 +66,7 @@ async fn quilt_get_loader(version: &str, launcher: &LauncherContext) -> Result<V
     let filepath = format!("org/quiltmc/quilt-loader/{version}/quilt-loader-{version}.jar");
     let path = &launcher.root_path / "libraries" / &filepath;
     if !path.0.exists() {
-        download(format!("https://maven.quiltmc.org/repository/release/{filepath}"), &path).await?;
+        launcher.download(&format!("https://maven.quiltmc.org/repository/release/{filepath}"), &path).await?;
     }
     loader.builtin_mods = Some(loader.get_mods_in_file(&path).ok().into_iter().flatten().collect());
 
@@ -89,6 +89,7 @@ impl FabricLikeInstaller {
         FabricLikeInstaller {
             meta_url: "https://meta.fabricmc.net/v2".to_string(),
             loader_artifact_name: "fabric-loader".to_string(),
+            game_jar_property: "fabric.gameJarPath".to_string(),
             #[cfg(feature = "mod_loaders")]
             get_loader: fabric_get_loader_boxpin
         }
@@ -99,6 +100,7 @@ impl FabricLikeInstaller {
         FabricLikeInstaller {
             meta_url: "https://meta.quiltmc.org/v3".to_string(),
             loader_artifact_name: "quilt-loader".to_string(),
+            game_jar_property: "loader.gameJarPath".to_string(),
             #[cfg(feature = "mod_loaders")]
             get_loader: quilt_get_loader_boxpin
         }
@@ -114,26 +116,45 @@ impl ComponentInstaller for FabricLikeInstaller {
 
     async fn get_suitable_loader_versions(&self, mc: &MinecraftInstallation) -> Result<Vec<String>> {
         let mcversion = mc.extra_data.version.as_ref().unwrap();
-        let versions: Vec<FabricLikeVersionInfo> = reqwest::get(
-            format!("{}/versions/loader/{}", self.meta_url, form_urlencoded::byte_serialize(mcversion.as_bytes()).collect::<String>())
+        let versions: Vec<FabricLikeVersionInfo> = mc.launcher.get(
+            &format!("{}/versions/loader/{}", self.meta_url, form_urlencoded::byte_serialize(mcversion.as_bytes()).collect::<String>())
         ).await?.json().await?;
         let res = versions.iter().map(|v|v.loader.version.clone()).collect();
         Ok(res)
     }
 
-    async fn install(&self, mc: &mut MinecraftInstallation, version: &str, download_channel: mpsc::UnboundedSender<DownloadAllMessage>) -> Result<()> {
+    async fn install(&self, mc: &mut MinecraftInstallation, version: &str, progress: &dyn ProgressSink) -> Result<()> {
         let mcversion = mc.extra_data.version.as_ref().unwrap();
-        let version_info: VersionJSON = reqwest::get(format!("{}/versions/loader/{}/{}/profile/json", self.meta_url,
+        let version_info: VersionJSON = mc.launcher.get(&format!("{}/versions/loader/{}/{}/profile/json", self.meta_url,
             form_urlencoded::byte_serialize(mcversion.as_bytes()).collect::<String>(),
             form_urlencoded::byte_serialize(version.as_bytes()).collect::<String>())
         ).await?.json().await?;
         mc.obj = merge_version_json(&mc.obj, &version_info)?;
         serde_json::to_writer(&std::fs::File::create(&mc.version_root / (mc.name.to_string() + ".json"))?, &mc.obj)?;
         let res = mc.install_libraries(&version_info.get_base().libraries, true)?;
-        download_all(&res, download_channel,
+        download_all(&res, DownloadPhase::LoaderLibraries, progress,
             mc.launcher.download_threads_per_file, mc.launcher.download_parallel_files, mc.launcher.download_retries,
-            mc.launcher.bmclapi_mirror.clone()
-        ).await?;
+            mc.launcher.mirror_provider.as_ref()
+        ).await?.into_result()?;
+        Ok(())
+    }
+
+    async fn install_server(&self, server: &mut ServerInstallation, version: &str, progress: &dyn ProgressSink) -> Result<()> {
+        let profile: VersionJSON = server.launcher.get(&format!("{}/versions/loader/{}/{}/server/json", self.meta_url,
+            form_urlencoded::byte_serialize(server.extra_data.version.as_bytes()).collect::<String>(),
+            form_urlencoded::byte_serialize(version.as_bytes()).collect::<String>())
+        ).await?.json().await?;
+        let res = server.launcher.get_library_resources(&profile.get_base().libraries, true)?;
+        download_all(&res, DownloadPhase::LoaderLibraries, progress,
+            server.launcher.download_threads_per_file, server.launcher.download_parallel_files, server.launcher.download_retries,
+            server.launcher.mirror_provider.as_ref()
+        ).await?.into_result()?;
+        // The loader finds the vanilla server by the property, like the server launcher jar from the official installer does.
+        server.extra_data.launch_target = ServerLaunchTarget::MainClass {
+            main_class: profile.get_base().main_class.clone(),
+            classpath: res.iter().map(|(_, path)|path.0.to_string_lossy().to_string()).collect(),
+            jvm_arguments: vec![format!("-D{}=server.jar", self.game_jar_property)]
+        };
         Ok(())
     }
 
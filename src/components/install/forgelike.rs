@@ -1,16 +1,15 @@
 //! Implementation of [ComponentInstaller] for Forge-like installers.
 
-use std::{collections::HashMap, ffi::OsString, io::Read, marker::PhantomData, path::PathBuf, process::Stdio};
+use std::{collections::HashMap, ffi::OsString, io::Read, path::PathBuf, process::Stdio};
 
 use anyhow::{anyhow, Result};
 use async_trait::async_trait;
 use fs_extra::dir::CopyOptions;
 use serde::{Deserialize, Serialize};
-use sha1::Sha1;
 use tempfile::TempDir;
-use tokio::{fs, process::Command, sync::mpsc};
+use tokio::{fs, process::Command};
 
-use crate::{components::mods::ModLoader, minecraft::{schemas::{Library, VersionJSON}, version::MinecraftInstallation}, utils::{check_hash, download_all, download_res, download_to_writer, expand_maven_id, maven_coord::ArtifactCoordinate, merge_version_json, BetterPath, DownloadAllMessage, PATH_DELIMITER}, LauncherContext};
+use crate::{components::mods::ModLoader, minecraft::{schemas::{Library, Resource, VersionJSON}, server::{ServerInstallation, ServerLaunchTarget}, version::MinecraftInstallation, garbage::list_files}, utils::{check_cancelled, download_all, DownloadPhase, expand_maven_id, maven_coord::ArtifactCoordinate, merge_version_json, wait_child, BetterPath, HashAlgorithm, ProgressSink, PATH_DELIMITER}, LauncherContext};
 
 use super::ComponentInstaller;
 
@@ -98,7 +97,7 @@ impl <T: ForgeLikeInstaller> ComponentInstaller for T {
         if major < 5 || (major == 5 && minor != 2) {
             return Ok(vec![]);
         }
-        let res = reqwest::get(format!("{}/{}/maven-metadata.xml", self.get_maven_group_url(), self.get_archive_base_name(&version))).await?.text().await?;
+        let res = mc.launcher.get(&format!("{}/{}/maven-metadata.xml", self.get_maven_group_url(), self.get_archive_base_name(&version))).await?.text().await?;
         let val = xmltree::Element::parse(res.as_bytes())?;
         Ok(val.get_child("versioning").unwrap()
             .get_child("versions").unwrap()
@@ -106,39 +105,48 @@ impl <T: ForgeLikeInstaller> ComponentInstaller for T {
             .filter(|v| self.match_version(&v, &mc.extra_data.version.as_ref().unwrap())).collect())
     }
 
-    async fn install(&self, mc: &mut MinecraftInstallation, version: &str, download_channel: mpsc::UnboundedSender<DownloadAllMessage>) -> Result<()> {
+    async fn install(&self, mc: &mut MinecraftInstallation, version: &str, progress: &dyn ProgressSink) -> Result<()> {
         let mcver = mc.extra_data.version.as_ref().unwrap().clone();
-        let mut tmpfile = tokio::fs::File::from_std(tempfile::tempfile()?);
-        let url = format!("{}/{1}/{version}/{}-{version}-installer.jar", self.get_maven_group_url(), self.get_archive_base_name(&mcver));
-        download_to_writer(&url, &mut tmpfile).await?;
+        let download_dir = BetterPath(tempfile::tempdir()?);
+        let installer = *(&download_dir / "installer.jar");
+        download_installer(self, mc.launcher, &mcver, version, &installer, progress).await?;
         let installer_dir = &BetterPath(tempfile::tempdir()?);
-        zip::ZipArchive::new(tmpfile.into_std().await)?.extract(installer_dir)?;
+        zip::ZipArchive::new(std::fs::File::open(&installer)?)?.extract(installer_dir)?;
         let metadata: InstallerProfile = serde_json::from_reader(std::fs::File::open(installer_dir / "install_profile.json")?)?;
         let result;
+        let mut generated = vec![];
         match metadata {
             InstallerProfile::New(metadata) => {
-                if metadata.data.contains_key("MOJMAPS") {
-                    let id = &metadata.data["MOJMAPS"].client;
-                    let id: String = id.chars().skip(1).take(id.len() - 2).collect();
-                    let path = &mc.launcher.root_path / "libraries" / expand_maven_id(&id);
-                    download_res(mc.obj.get_base().downloads.client_mappings.as_ref().unwrap(), &path).await?;
-                }
                 let maven_dir = installer_dir / "maven";
                 if let Ok(f) = fs::metadata(&maven_dir).await && f.is_dir() {
                     fs_extra::dir::copy(&maven_dir, &mc.launcher.root_path / "libraries", &CopyOptions::new().content_only(true))?;
+                    generated.extend(list_files(&maven_dir).await?.into_iter()
+                        .filter_map(|(path, _)|Some(path.strip_prefix(&maven_dir.0).ok()?.to_string_lossy().replace('\\', "/"))));
                 }
+                // Processors write their outputs to the artifacts in the data, like the patched client jar.
+                generated.extend(metadata.data.values()
+                    .filter_map(|v|v.client.strip_prefix('[')?.strip_suffix(']').map(expand_maven_id)));
                 let mut res = mc.install_libraries(&metadata.libraries, false)?;
                 let target = &mc.obj;
                 let source: VersionJSON = serde_json::from_reader(std::fs::File::open(installer_dir / "version.json")?)?;
                 result = merge_version_json(target, &source)?;
                 res.extend(mc.install_libraries(&source.get_base().libraries, false)?);
+                if metadata.data.contains_key("MOJMAPS") {
+                    let id = &metadata.data["MOJMAPS"].client;
+                    let id: String = id.chars().skip(1).take(id.len() - 2).collect();
+                    let path = &mc.launcher.root_path / "libraries" / expand_maven_id(&id);
+                    res.push((mc.obj.get_base().downloads.client_mappings.clone().unwrap(), *path));
+                }
                 download_all(
-                    &res, download_channel, mc.launcher.download_threads_per_file,
+                    &res, DownloadPhase::LoaderLibraries, progress, mc.launcher.download_threads_per_file,
                     mc.launcher.download_parallel_files, mc.launcher.download_retries,
-                    mc.launcher.bmclapi_mirror.clone()
-                ).await?;
+                    mc.launcher.mirror_provider.as_ref()
+                ).await?.into_result()?;
 
+                // Processors need a Java that runs the version, which isn't always the one in `PATH`.
+                let java = mc.get_java(progress).await?;
                 for processor in &metadata.processors {
+                    check_cancelled(progress)?;
                     if processor.args.contains(&"DOWNLOAD_MOJMAPS".to_string()) {
                         continue;
                     }
@@ -149,10 +157,10 @@ impl <T: ForgeLikeInstaller> ComponentInstaller for T {
                             let outputs = &processor.outputs;
                             res = true;
                             for (k, v) in outputs {
-                                res = res && check_hash(
+                                res = res && HashAlgorithm::Sha1.check(
                                     &BetterPath(PathBuf::from(transform_arguments(&k, installer_dir, &mc, &metadata))),
                                     &transform_arguments(&v, installer_dir, &mc, &metadata).into_string().unwrap(),
-                                    0, PhantomData::<Sha1>
+                                    0
                                 ).await;
                             }
                         }
@@ -170,13 +178,13 @@ impl <T: ForgeLikeInstaller> ComponentInstaller for T {
                                 .collect(),
                             get_main_class(&jar)?
                         ], processor.args.iter().map(|v|transform_arguments(v, &installer_dir, &mc, &metadata)).collect()].concat();
-                        if !Command::new("java")
+                        let mut child = Command::new(&java)
                             .args(args)
                             .stdout(Stdio::inherit())
                             .stderr(Stdio::inherit())
                             .stdin(Stdio::null())
-                            .spawn()?
-                            .wait().await?.success() {
+                            .spawn()?;
+                        if !wait_child(progress, &mut child).await?.success() {
                                 return Err(anyhow!(t!("A processor failed to run!")).into()) // TODO: i18n
                             }
                     }
@@ -190,10 +198,35 @@ impl <T: ForgeLikeInstaller> ComponentInstaller for T {
                 let source: VersionJSON = metadata.version_info;
                 result = merge_version_json(target, &source)?;
                 tokio::fs::copy(installer_dir / metadata.install.file_path, &mc.launcher.root_path / "libraries" / metadata.install.path.to_path()).await?;
+                generated.push(metadata.install.path.to_path());
             }
         }
         serde_json::to_writer(&std::fs::File::create(&mc.version_root / (mc.name.to_string() + ".json"))?, &result)?;
         mc.obj = result;
+        mc.extra_data.add_generated_libraries(generated);
+        Ok(())
+    }
+
+    async fn install_server(&self, server: &mut ServerInstallation, version: &str, progress: &dyn ProgressSink) -> Result<()> {
+        let installer_dir = BetterPath(tempfile::tempdir()?);
+        let installer = *(&installer_dir / "installer.jar");
+        download_installer(self, server.launcher, &server.extra_data.version, version, &installer, progress).await?;
+        let java = server.get_java(progress).await?;
+        check_cancelled(progress)?;
+        // The installer runs the processors for the server side itself.
+        let mut child = Command::new(java)
+            .arg("-jar")
+            .arg(&installer.0)
+            .arg("--installServer")
+            .arg(server.get_dir())
+            .current_dir(&installer_dir)
+            .stdin(Stdio::null())
+            .spawn()?;
+        let status = wait_child(progress, &mut child).await?;
+        if !status.success() {
+            return Err(anyhow!(t!("server.installer_failed", status = status)));
+        }
+        server.extra_data.launch_target = find_server_launch_target(&server.root, version).await?;
         Ok(())
     }
 
@@ -202,6 +235,46 @@ impl <T: ForgeLikeInstaller> ComponentInstaller for T {
     }
 }
 
+fn get_installer_url<T: ForgeLikeInstaller>(installer: &T, mcver: &str, version: &str) -> String {
+    format!("{}/{1}/{version}/{}-{version}-installer.jar", installer.get_maven_group_url(), installer.get_archive_base_name(mcver))
+}
+
+/// Download the installer to `path`, reporting it as [DownloadPhase::LoaderInstaller].
+async fn download_installer<T: ForgeLikeInstaller>(installer: &T, launcher: &LauncherContext, mcver: &str, version: &str, path: &BetterPath, progress: &dyn ProgressSink) -> Result<()> {
+    let res = Resource {
+        url: get_installer_url(installer, mcver, version),
+        ..Default::default()
+    };
+    download_all(
+        &vec![(res, path.clone())], DownloadPhase::LoaderInstaller, progress,
+        launcher.download_threads_per_file, launcher.download_parallel_files,
+        launcher.download_retries, launcher.mirror_provider.as_ref()
+    ).await?.into_result()?;
+    Ok(())
+}
+
+/// Newer installers make a script that reads arguments from `@libraries/.../unix_args.txt`, and older ones make a jar.
+async fn find_server_launch_target(dir: &BetterPath, version: &str) -> Result<ServerLaunchTarget> {
+    let script = if cfg!(target_os = "windows") { "run.bat" } else { "run.sh" };
+    if let Ok(script) = fs::read_to_string(dir / script).await
+        && let Some(path) = script.split_whitespace().find_map(|v|v.strip_prefix("@libraries/")) {
+        return Ok(ServerLaunchTarget::ArgsFile { path: format!("libraries/{path}") });
+    }
+    let mut jars = vec![];
+    let mut entries = fs::read_dir(dir).await?;
+    while let Some(entry) = entries.next_entry().await? {
+        let name = entry.file_name().to_string_lossy().to_string();
+        if name.ends_with(".jar") && name.contains(version) && !name.contains("installer") {
+            jars.push(name);
+        }
+    }
+    // Some installers make both a shim jar and a normal jar, and the shim one should be run.
+    jars.sort_by_key(|v|!v.ends_with("-shim.jar"));
+    jars.into_iter().next()
+        .map(|path|ServerLaunchTarget::Jar { path })
+        .ok_or(anyhow!(t!("server.launch_target_not_found")))
+}
+
 fn get_main_class(path: &BetterPath) -> Result<OsString> {
     let mut file = zip::ZipArchive::new(std::fs::File::open(path)?)?;
     let mut manifest = file.by_name("META-INF/MANIFEST.MF")?;
@@ -6,10 +6,10 @@ pub(crate) mod curseforge;
 
 use std::collections::HashMap;
 
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use async_trait::async_trait;
 
-use crate::{minecraft::version::MinecraftInstallation, utils::BetterPath, LauncherContext};
+use crate::{minecraft::{schemas::Resource, version::MinecraftInstallation}, utils::{download_all, DownloadPhase, BetterPath, ProgressSink}, LauncherContext};
 
 /// Type of contents.
 #[derive(PartialEq, Eq, Hash)]
@@ -66,6 +66,16 @@ pub trait ContentVersion: Send + Sync {
     fn get_version_file_url(&self) -> String;
     /// Get file SHA1.
     fn get_version_file_sha1(&self) -> String;
+    /// Get the file as a [Resource] with all known hashes.
+    /// Download it with [LauncherContext::download_content_version], which checks it with the strongest hash.
+    /// By default, it only has [Self::get_version_file_sha1].
+    fn get_version_file_resource(&self) -> Resource {
+        Resource {
+            url: self.get_version_file_url(),
+            sha1: self.get_version_file_sha1(),
+            ..Default::default()
+        }
+    }
     /// Get file name.
     fn get_version_file_name(&self) -> String;
     /// Get changelog in HTML.
@@ -114,3 +124,22 @@ pub trait ContentService: Send + Sync {
     /// Get a [ContentVersion] by ID.
     async fn get_content_version_by_id(&self, content_id: &str, id: &str, launcher: &LauncherContext) -> Result<Option<Box<dyn ContentVersion>>>;
 }
+
+impl LauncherContext {
+    /// Download the file of a [ContentVersion] into `dir`, like the `mods` dir of an installation, and return its path.
+    /// It's checked with the strongest hash from [ContentVersion::get_version_file_resource].
+    pub async fn download_content_version(&self, version: &dyn ContentVersion, dir: &BetterPath, progress: &dyn ProgressSink) -> Result<BetterPath> {
+        let name = version.get_version_file_name();
+        // The name is from the service, so it mustn't escape `dir`.
+        if std::path::Path::new(&name).file_name().is_none_or(|v|*v != *name) {
+            return Err(anyhow!(t!("content.invalid_file_name", name = name)));
+        }
+        let path = *(dir / &name);
+        download_all(
+            &vec![(version.get_version_file_resource(), path.clone())], DownloadPhase::Content, progress,
+            self.download_threads_per_file, self.download_parallel_files,
+            self.download_retries, self.mirror_provider.as_ref()
+        ).await?.into_result()?;
+        Ok(path)
+    }
+}
@@ -3,12 +3,13 @@ use std::{collections::HashMap, fmt::Debug, sync::LazyLock};
 use anyhow::Result;
 use async_trait::async_trait;
 use murmur2::murmur2;
+use reqwest::Method;
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
 use serde_repr::{Deserialize_repr, Serialize_repr};
 use tokio::{fs::File, io::AsyncReadExt};
 
-use crate::{minecraft::version::MinecraftInstallation, utils::BetterPath, LauncherContext};
+use crate::{minecraft::{schemas::Resource, version::MinecraftInstallation}, utils::BetterPath, LauncherContext};
 
 use super::{Content, ContentDependency, ContentService, ContentType, ContentVersion, Screenshot};
 #[cfg(feature="mod_loaders")]
@@ -188,13 +189,13 @@ impl Debug for CurseforgeModFile {
 
 impl CurseforgeMod {
     async fn from_id(id: &str, launcher: &LauncherContext) -> Result<Self> {
-        Ok(launcher.http_client.get(format!("https://api.curseforge.com/v1/mods/{id}")).header("x-api-key", API_KEY).send().await?.json::<DataWrapped<_>>().await?.data)
+        Ok(launcher.send(Method::GET, &format!("https://api.curseforge.com/v1/mods/{id}"), |v|v.header("x-api-key", API_KEY)).await?.json::<DataWrapped<_>>().await?.data)
     }
 }
 
 impl CurseforgeModFile {
     async fn from_id(modid: &str, id: &str, launcher: &LauncherContext) -> Result<Self> {
-        Ok(launcher.http_client.get(format!("https://api.curseforge.com/v1/mods/{modid}/files/{id}")).header("x-api-key", API_KEY).send().await?.json::<DataWrapped<_>>().await?.data)
+        Ok(launcher.send(Method::GET, &format!("https://api.curseforge.com/v1/mods/{modid}/files/{id}"), |v|v.header("x-api-key", API_KEY)).await?.json::<DataWrapped<_>>().await?.data)
     }
 }
 
@@ -225,7 +226,7 @@ impl Content for CurseforgeMod {
             } else {
                 vec![("index", index.to_string())]
             };
-            let versions: DataWrapped<Vec<CurseforgeModFile>> = launcher.http_client.get(format!("https://api.curseforge.com/v1/mods/{}/files", self.id)).header("x-api-key", API_KEY).query(&query).send().await?.json().await?;
+            let versions: DataWrapped<Vec<CurseforgeModFile>> = launcher.send(Method::GET, &format!("https://api.curseforge.com/v1/mods/{}/files", self.id), |v|v.header("x-api-key", API_KEY).query(&query)).await?.json().await?;
             let length = versions.data.len();
             let versions: Vec<_> = versions.data.into_iter().filter(|v|v.is_available).map(Box::new).map(|v|v as Box<dyn ContentVersion>).collect();
             index += 50;
@@ -243,7 +244,7 @@ impl Content for CurseforgeMod {
         self.summary.clone()
     }
     async fn get_body(&self, launcher: &LauncherContext) -> Result<String> {
-        let res: DataWrapped<String> = launcher.http_client.get(format!("https://api.curseforge.com/v1/mods/{}/description", self.id)).header("x-api-key", API_KEY).send().await?.json().await?;
+        let res: DataWrapped<String> = launcher.send(Method::GET, &format!("https://api.curseforge.com/v1/mods/{}/description", self.id), |v|v.header("x-api-key", API_KEY)).await?.json().await?;
         Ok(res.data)
     }
     fn get_icon_url(&self) -> Option<String> {
@@ -299,11 +300,20 @@ impl ContentVersion for CurseforgeModFile {
     fn get_version_file_sha1(&self) -> String {
         self.hashes.iter().find(|v|v.algo == Algorithm::SHA1).unwrap().value.clone()
     }
+    fn get_version_file_resource(&self) -> Resource {
+        // CurseForge only provides SHA1 and MD5.
+        Resource {
+            url: self.get_version_file_url(),
+            sha1: self.hashes.iter().find(|v|v.algo == Algorithm::SHA1).map(|v|v.value.clone()).unwrap_or_default(),
+            size: self.file_length as usize,
+            ..Default::default()
+        }
+    }
     fn get_version_file_name(&self) -> String {
         self.file_name.clone()
     }
     async fn get_version_changelog(&self, launcher: &LauncherContext) -> Result<String> {
-        let res: DataWrapped<String> = launcher.http_client.get(format!("https://api.curseforge.com/v1/mods/{}/files/{}/changelog", self.mod_id, self.id)).send().await?.json().await?;
+        let res: DataWrapped<String> = launcher.get(&format!("https://api.curseforge.com/v1/mods/{}/files/{}/changelog", self.mod_id, self.id)).await?.json().await?;
         Ok(res.data)
     }
     fn get_version_number(&self) -> String {
@@ -356,7 +366,7 @@ impl ContentService for CurseforgeContentService {
                 query.push(("modLoaderType", LOADER_TO_CURSEFORGE[&loader.name].to_string()));
             }
         }
-        let results: DataWrapped<Vec<CurseforgeMod>> = launcher.http_client.get("https://api.curseforge.com/v1/mods/search").header("x-api-key", API_KEY).query(&query).send().await?.json().await?;
+        let results: DataWrapped<Vec<CurseforgeMod>> = launcher.send(Method::GET, "https://api.curseforge.com/v1/mods/search", |v|v.header("x-api-key", API_KEY).query(&query)).await?.json().await?;
         Ok(results.data.into_iter().map(|v|Box::new(v) as Box<dyn Content>).collect())
     }
     
@@ -377,9 +387,9 @@ impl ContentService for CurseforgeContentService {
         File::open(path).await?.read_to_string(&mut data).await?;
         let data = data.into_bytes().into_iter().filter(|v|[0x9, 0xa, 0xd, 0x20].contains(v)).collect::<Vec<_>>();
         let mm2 = murmur2(&data, 1);
-        let res: Value = launcher.http_client.post("https://api.curseforge.com/v1/fingerprints").header("x-api-key", API_KEY).json(&json!({
+        let res: Value = launcher.send(Method::POST, "https://api.curseforge.com/v1/fingerprints", |v|v.header("x-api-key", API_KEY).json(&json!({
             "fingerprints": [mm2]
-        })).send().await?.json().await?;
+        }))).await?.json().await?;
         let exact_matches = res["data"]["exactMatches"].as_array().unwrap();
         if exact_matches.len() == 0 {
             Ok(None)
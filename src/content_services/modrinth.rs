@@ -1,16 +1,16 @@
-use std::{collections::HashMap, fmt::Debug, sync::LazyLock};
+use std::{collections::{BTreeMap, HashMap}, fmt::Debug, sync::LazyLock};
 
 use anyhow::{anyhow, Error, Result};
 use async_trait::async_trait;
 use futures_util::io::AllowStdIo;
 use markdown_it::MarkdownIt;
-use reqwest::StatusCode;
+use reqwest::{Method, StatusCode};
 use serde::{Deserialize, Serialize};
 use sha1::{Digest, Sha1};
 use tokio::fs::File;
 use tokio_util::compat::TokioAsyncReadCompatExt;
 
-use crate::{minecraft::version::MinecraftInstallation, utils::BetterPath, LauncherContext};
+use crate::{minecraft::{schemas::Resource, version::MinecraftInstallation}, utils::{BetterPath, HashAlgorithm}, LauncherContext};
 
 use super::{Content, ContentDependency, ContentService, ContentType, ContentVersion, Screenshot};
 
@@ -226,13 +226,13 @@ struct ModrinthContentVersion(ModrinthVersionModel, ModrinthFile);
 
 impl ModrinthProject {
     async fn from_id(id: &str, launcher: &LauncherContext) -> Result<Self> {
-        Ok(launcher.http_client.get(format!("https://api.modrinth.com/v2/project/{id}")).send().await?.json().await?)
+        Ok(launcher.get(&format!("https://api.modrinth.com/v2/project/{id}")).await?.json().await?)
     }
 }
 
 impl ModrinthContentVersion {
     async fn from_id(id: &str, launcher: &LauncherContext) -> Result<Self> {
-        Ok(Self::new(launcher.http_client.get(format!("https://api.modrinth.com/v2/version/{id}")).send().await?.json().await?))
+        Ok(Self::new(launcher.get(&format!("https://api.modrinth.com/v2/version/{id}")).await?.json().await?))
     }
 }
 
@@ -255,7 +255,7 @@ impl Content for ModrinthProject {
         } else {
             vec![]
         };
-        let versions: Vec<ModrinthVersionModel> = launcher.http_client.get(format!("https://api.modrinth.com/v2/project/{}/version", self.slug)).query(&query).send().await?.json().await?;
+        let versions: Vec<ModrinthVersionModel> = launcher.send(Method::GET, &format!("https://api.modrinth.com/v2/project/{}/version", self.slug), |v|v.query(&query)).await?.json().await?;
         Ok(versions.into_iter().map(|v|Box::new(ModrinthContentVersion::new(v)) as Box<dyn ContentVersion>).collect())
     }
     fn get_title(&self) -> String {
@@ -335,6 +335,14 @@ impl ContentVersion for ModrinthContentVersion {
     fn get_version_file_sha1(&self) -> String {
         self.1.hashes.sha1.clone()
     }
+    fn get_version_file_resource(&self) -> Resource {
+        Resource {
+            url: self.1.url.clone(),
+            sha1: self.1.hashes.sha1.clone(),
+            size: self.1.size,
+            hashes: BTreeMap::from([(HashAlgorithm::Sha512, self.1.hashes.sha512.clone())])
+        }
+    }
     fn get_version_file_name(&self) -> String {
         self.1.filename.clone()
     }
@@ -403,13 +411,13 @@ impl ContentService for ModrinthContentService {
             #[cfg(feature="mod_loaders")]
             facets.push(loaders);
         }
-        let results: SearchResults = launcher.http_client.get("https://api.modrinth.com/v2/search").query(&[
-            ("query", name),
+        let results: SearchResults = launcher.send(Method::GET, "https://api.modrinth.com/v2/search", |v|v.query(&[
+            ("query", name.clone()),
             ("facets", serde_json::to_string(&facets).unwrap()),
             ("offset", skip.to_string()),
             ("limit", limit.to_string()),
             ("index", self.get_sort_fields()[sort_field].clone()),
-        ]).send().await?.json().await?;
+        ])).await?.json().await?;
         let res = futures::future::join_all(results.hits.iter().map(|v|ModrinthProject::from_id(&v.project_id, &launcher))).await;
         let errors: Vec<&Error> = res.iter().filter(|v|v.is_err()).map(|v|v.as_ref().unwrap_err()).collect();
         if !errors.is_empty() {
@@ -434,7 +442,7 @@ impl ContentService for ModrinthContentService {
     async fn get_content_version_from_file(&self, path: &BetterPath, launcher: &LauncherContext) -> Result<Option<Box<dyn ContentVersion>>> {
         let mut sha1 = AllowStdIo::new(Sha1::new());
         futures_util::io::copy(File::open(path).await?.compat(), &mut sha1).await?;
-        let res = launcher.http_client.get(format!("https://api.modrinth.com/v2/version/version_file/{:X}?algorithm=sha1", sha1.into_inner().finalize())).send().await?;
+        let res = launcher.get(&format!("https://api.modrinth.com/v2/version/version_file/{:X}?algorithm=sha1", sha1.into_inner().finalize())).await?;
         if res.status() == StatusCode::NOT_FOUND {
             Ok(None)
         } else {
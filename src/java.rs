@@ -0,0 +1,5 @@
+//! Things about Java.
+
+pub mod installation;
+pub mod runtime;
+pub mod tuning;
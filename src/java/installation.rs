@@ -0,0 +1,174 @@
+//! Java installations on the system.
+
+use std::{collections::HashMap, ffi::OsString, path::{Path, PathBuf}};
+
+use anyhow::{anyhow, Result};
+use tokio::{fs, process::Command};
+
+use crate::{minecraft::schemas::JavaInfo, utils::{parse_java_major_version, BetterPath}, LauncherContext};
+
+#[cfg(not(target_os="windows"))]
+const JAVA_EXECUTABLE: &str = "java";
+
+#[cfg(target_os="windows")]
+const JAVA_EXECUTABLE: &str = "java.exe";
+
+/// A Java installation.
+#[derive(Debug, Clone)]
+pub struct JavaInstallation {
+    /// The java executable.
+    pub path: BetterPath,
+    /// The Java home.
+    pub home: BetterPath,
+    /// The full version, like `17.0.8`.
+    pub version: String,
+    /// The major version, like `17`.
+    pub major_version: usize,
+    /// The vendor, like `Eclipse Adoptium`.
+    pub vendor: Option<String>,
+    /// The architecture, like `x86_64` or `aarch64`.
+    pub arch: Option<String>
+}
+
+impl JavaInstallation {
+    /// Get the information of a java executable.
+    /// It reads the `release` file in the Java home, and runs `java -XshowSettings:properties -version` if it fails.
+    pub async fn probe(java: &Path) -> Result<Self> {
+        Self::probe_with(java, true).await
+    }
+
+    /// Like [Self::probe], but it fails instead of running the java if `run_java` is false.
+    pub(crate) async fn probe_with(java: &Path, run_java: bool) -> Result<Self> {
+        let path = fs::canonicalize(java).await?;
+        let home = path.parent().and_then(Path::parent).ok_or(anyhow!(t!("java.invalid", path = java.display())))?.to_path_buf();
+        if let Ok(release) = fs::read_to_string(home.join("release")).await {
+            let release = parse_release(&release);
+            if let Some(version) = release.get("JAVA_VERSION") && let Some(major_version) = parse_java_major_version(version) {
+                return Ok(Self {
+                    path: BetterPath(path),
+                    home: BetterPath(home),
+                    version: version.clone(),
+                    major_version,
+                    vendor: release.get("IMPLEMENTOR").cloned(),
+                    arch: release.get("OS_ARCH").cloned()
+                });
+            }
+        }
+        if !run_java {
+            return Err(anyhow!(t!("java.invalid", path = java.display())));
+        }
+        let output = Command::new(&path).args(["-XshowSettings:properties", "-version"]).output().await?;
+        let properties = parse_properties(&String::from_utf8_lossy(&output.stderr));
+        let version = properties.get("java.version").ok_or(anyhow!(t!("java.invalid", path = java.display())))?;
+        Ok(Self {
+            path: BetterPath(path),
+            home: BetterPath(properties.get("java.home").map_or(home, PathBuf::from)),
+            version: version.clone(),
+            major_version: parse_java_major_version(version).ok_or(anyhow!(t!("java.invalid", path = java.display())))?,
+            vendor: properties.get("java.vendor").cloned(),
+            arch: properties.get("os.arch").cloned()
+        })
+    }
+
+    /// Check if it can run a Minecraft version that requires the [JavaInfo].
+    /// Newer Java works, except for the versions that require Java 8.
+    pub fn is_suitable_for(&self, info: &JavaInfo) -> bool {
+        info.major_version == 0 || self.major_version == info.major_version
+            || (info.major_version > 8 && self.major_version > info.major_version)
+    }
+}
+
+fn parse_release(content: &str) -> HashMap<String, String> {
+    content.lines()
+        .filter_map(|v|v.split_once('='))
+        .map(|(k, v)|(k.trim().to_string(), v.trim().trim_matches('"').to_string()))
+        .collect()
+}
+
+fn parse_properties(content: &str) -> HashMap<String, String> {
+    content.lines()
+        .filter_map(|v|v.split_once(" = "))
+        .map(|(k, v)|(k.trim().to_string(), v.trim().to_string()))
+        .collect()
+}
+
+fn get_candidate_homes(root_path: &BetterPath) -> Vec<PathBuf> {
+    let mut homes = vec![];
+    if let Some(home) = std::env::var_os("JAVA_HOME") {
+        homes.push(PathBuf::from(home));
+    }
+    let mut dirs = vec![PathBuf::from("/usr/lib/jvm"), (root_path / "runtime").0];
+    if let Some(home) = std::env::var_os("HOME") {
+        let home = PathBuf::from(home);
+        dirs.push(home.join(".sdkman/candidates/java"));
+        dirs.push(home.join(".jdks"));
+    }
+    for dir in dirs {
+        if let Ok(entries) = std::fs::read_dir(dir) {
+            homes.extend(entries.flatten().map(|v|v.path()));
+        }
+    }
+    homes
+}
+
+fn get_candidate_executables(root_path: &BetterPath) -> Vec<PathBuf> {
+    let mut ret: Vec<PathBuf> = get_candidate_homes(root_path).into_iter()
+        .flat_map(|v|[v.join("bin").join(JAVA_EXECUTABLE), v.join("jre.bundle/Contents/Home/bin").join(JAVA_EXECUTABLE)])
+        .collect();
+    if let Some(path) = std::env::var_os("PATH") {
+        ret.extend(std::env::split_paths(&path).map(|v|v.join(JAVA_EXECUTABLE)));
+    }
+    ret
+}
+
+impl LauncherContext {
+    /// Find Java installations on the system.
+    /// It looks in `JAVA_HOME`, `/usr/lib/jvm`, `~/.sdkman`, `~/.jdks`, `PATH` and the Java runtimes provided by Mojang.
+    pub async fn find_java_installations(&self) -> Vec<JavaInstallation> {
+        self.find_java_installations_with(true).await
+    }
+
+    /// Like [Self::find_java_installations], but Javas are probed with [JavaInstallation::probe_with].
+    pub(crate) async fn find_java_installations_with(&self, run_java: bool) -> Vec<JavaInstallation> {
+        let mut ret: Vec<JavaInstallation> = vec![];
+        for java in get_candidate_executables(&self.root_path) {
+            if !java.is_file() {
+                continue;
+            }
+            if let Ok(canonical) = fs::canonicalize(&java).await && ret.iter().any(|v|v.path.0 == canonical) {
+                continue;
+            }
+            if let Ok(installation) = JavaInstallation::probe_with(&java, run_java).await {
+                ret.push(installation);
+            }
+        }
+        ret
+    }
+}
+
+/// Select the most suitable [JavaInstallation] for the [JavaInfo].
+/// A Java with the same major version is preferred. Otherwise, the oldest newer Java is selected, unless Java 8 is required.
+pub fn select_java<'a>(installations: &'a [JavaInstallation], info: &JavaInfo) -> Option<&'a JavaInstallation> {
+    if let Some(java) = installations.iter().find(|v|v.major_version == info.major_version) {
+        return Some(java);
+    }
+    installations.iter()
+        .filter(|v|v.is_suitable_for(info))
+        .min_by_key(|v|v.major_version)
+}
+
+/// Find the java executable in `PATH` if `java` is only a name.
+pub(crate) fn which_java(java: &OsString) -> PathBuf {
+    let path = PathBuf::from(java);
+    if path.components().count() > 1 {
+        return path;
+    }
+    if let Some(paths) = std::env::var_os("PATH") {
+        for dir in std::env::split_paths(&paths) {
+            if dir.join(&path).is_file() {
+                return dir.join(&path);
+            }
+        }
+    }
+    path
+}
@@ -0,0 +1,159 @@
+//! Java runtimes provided by Mojang.
+//! They are installed in `<root>/runtime/<component>`.
+
+use std::collections::HashMap;
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use tokio::fs;
+
+use crate::{minecraft::schemas::Resource, utils::{check_res, download_all, DownloadPhase, BetterPath, ProgressSink}, LauncherContext};
+
+const JAVA_RUNTIME_MANIFEST_URL: &str = "https://piston-meta.mojang.com/v1/products/java-runtime/2ec0cc96c44e5a76b9c8b7c39df7210883d12871/all.json";
+
+/// The version of a [JavaRuntimeInfo].
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct JavaRuntimeVersion {
+    /// Version name, like `17.0.8`.
+    pub name: String,
+    /// Release time.
+    pub released: String
+}
+
+/// A Java runtime in Mojang's java-runtime manifest.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct JavaRuntimeInfo {
+    /// The manifest that lists all the files.
+    pub manifest: Resource,
+    /// The version.
+    pub version: JavaRuntimeVersion
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct RuntimeFileDownloads {
+    raw: Resource
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "type", rename_all = "lowercase")]
+enum RuntimeFile {
+    File {
+        #[serde(default)]
+        executable: bool,
+        downloads: RuntimeFileDownloads
+    },
+    Directory,
+    Link {
+        target: String
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct RuntimeManifest {
+    files: HashMap<String, RuntimeFile>
+}
+
+/// Get the platform name used in Mojang's java-runtime manifest.
+/// Returns [None] if Mojang doesn't provide Java runtimes for the current platform.
+pub fn get_runtime_platform() -> Option<&'static str> {
+    if cfg!(all(target_os = "linux", target_arch = "x86_64")) {
+        Some("linux")
+    } else if cfg!(all(target_os = "linux", target_arch = "x86")) {
+        Some("linux-i386")
+    } else if cfg!(all(target_os = "macos", target_arch = "x86_64")) {
+        Some("mac-os")
+    } else if cfg!(all(target_os = "macos", target_arch = "aarch64")) {
+        Some("mac-os-arm64")
+    } else if cfg!(all(target_os = "windows", target_arch = "x86_64")) {
+        Some("windows-x64")
+    } else if cfg!(all(target_os = "windows", target_arch = "x86")) {
+        Some("windows-x86")
+    } else if cfg!(all(target_os = "windows", target_arch = "aarch64")) {
+        Some("windows-arm64")
+    } else {
+        None
+    }
+}
+
+fn get_java_executable(home: &BetterPath) -> BetterPath {
+    if cfg!(target_os = "macos") {
+        *(home / "jre.bundle/Contents/Home/bin/java")
+    } else if cfg!(target_os = "windows") {
+        *(home / "bin/javaw.exe")
+    } else {
+        *(home / "bin/java")
+    }
+}
+
+impl LauncherContext {
+    /// List the Java runtimes for the current platform.
+    /// The key is the component name like `java-runtime-delta`, which is used in [crate::minecraft::schemas::JavaInfo::component].
+    pub async fn list_java_runtimes(&self) -> Result<HashMap<String, Vec<JavaRuntimeInfo>>> {
+        let platform = get_runtime_platform().ok_or(anyhow!(t!("java.runtime.unsupported_platform")))?;
+        let mut all: HashMap<String, HashMap<String, Vec<JavaRuntimeInfo>>> = self
+            .get(JAVA_RUNTIME_MANIFEST_URL).await?
+            .json().await?;
+        Ok(all.remove(platform).unwrap_or_default())
+    }
+
+    /// Get the java executable of an installed Java runtime.
+    /// Returns [None] if it isn't installed.
+    pub fn get_java_runtime(&self, component: &str) -> Option<BetterPath> {
+        let java = get_java_executable(&(&self.root_path / "runtime" / component));
+        if java.0.is_file() && (&self.root_path / "runtime" / format!("{component}.json")).0.is_file() {
+            Some(java)
+        } else {
+            None
+        }
+    }
+
+    /// Install or repair a Java runtime.
+    /// Returns the java executable.
+    pub async fn install_java_runtime(&self, component: &str, progress: &dyn ProgressSink) -> Result<BetterPath> {
+        let info = self.list_java_runtimes().await?
+            .remove(component)
+            .and_then(|v|v.into_iter().next())
+            .ok_or(anyhow!(t!("java.runtime.not_found", component = component)))?;
+        let runtime_dir = *(&self.root_path / "runtime");
+        let manifest_path = *(&runtime_dir / format!("{component}.json"));
+        if !check_res(&info.manifest, &manifest_path).await {
+            self.download(&info.manifest.url, &manifest_path).await?;
+            if !check_res(&info.manifest, &manifest_path).await {
+                let _ = fs::remove_file(&manifest_path).await;
+                return Err(anyhow!(t!("java.runtime.broken_manifest", component = component)));
+            }
+        }
+        let manifest: RuntimeManifest = serde_json::from_slice(&fs::read(&manifest_path).await?)?;
+        let home = *(&runtime_dir / component);
+        let mut resources = vec![];
+        for (path, file) in &manifest.files {
+            match file {
+                RuntimeFile::Directory => fs::create_dir_all(&home / path).await?,
+                RuntimeFile::File { downloads, .. } => resources.push((downloads.raw.clone(), *(&home / path))),
+                RuntimeFile::Link { .. } => ()
+            }
+        }
+        download_all(
+            &resources, DownloadPhase::JavaRuntime, progress,
+            self.download_threads_per_file, self.download_parallel_files,
+            self.download_retries, self.mirror_provider.as_ref()
+        ).await?.into_result()?;
+        #[cfg(unix)]
+        for (path, file) in &manifest.files {
+            use std::os::unix::fs::PermissionsExt;
+            match file {
+                RuntimeFile::File { executable: true, .. } => {
+                    fs::set_permissions(&home / path, std::fs::Permissions::from_mode(0o755)).await?;
+                },
+                RuntimeFile::Link { target } => {
+                    let link = &home / path;
+                    if fs::symlink_metadata(&link).await.is_err() {
+                        fs::symlink(target, &link).await?;
+                    }
+                },
+                _ => ()
+            }
+        }
+        Ok(get_java_executable(&home))
+    }
+}
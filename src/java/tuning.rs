@@ -0,0 +1,127 @@
+//! JVM memory and garbage collector settings.
+
+use std::ffi::OsString;
+
+use serde::{Deserialize, Serialize};
+use sysinfo::System;
+
+use crate::utils::BetterPath;
+
+/// The max heap size in MiB that [auto_max_heap] chooses at most.
+/// Larger heaps make GC pauses longer without helping the game.
+const MAX_AUTO_HEAP: u64 = 16384;
+
+/// Heap size settings in MiB.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct MemorySettings {
+    /// Choose the max heap size by [auto_max_heap].
+    /// [Self::max] is ignored if it's enabled.
+    #[serde(default)]
+    pub auto: bool,
+    /// Min heap size, for `-Xms`.
+    pub min: Option<u64>,
+    /// Max heap size, for `-Xmx`.
+    pub max: Option<u64>
+}
+
+impl MemorySettings {
+    /// Get the JVM arguments.
+    ///
+    /// # Arguments
+    /// * `mod_count` - The count of installed mods, used in auto mode.
+    pub fn to_args(&self, mod_count: usize) -> Vec<OsString> {
+        let max = if self.auto {
+            Some(auto_max_heap(get_total_memory(), mod_count))
+        } else {
+            self.max
+        };
+        let mut ret = vec![];
+        if let Some(min) = self.min {
+            // The JVM refuses to start if min heap is larger than max heap.
+            ret.push(OsString::from(format!("-Xms{}m", max.map_or(min, |max|min.min(max)))));
+        }
+        if let Some(max) = max {
+            ret.push(OsString::from(format!("-Xmx{max}m")));
+        }
+        ret
+    }
+}
+
+/// Get the total system memory in MiB.
+/// Returns [None] if it's unknown.
+pub fn get_total_memory() -> Option<u64> {
+    let mut system = System::new();
+    system.refresh_memory();
+    let total = system.total_memory() / 1024 / 1024;
+    (total != 0).then_some(total)
+}
+
+/// Choose a max heap size in MiB.
+/// Vanilla gets 2 GiB, and modded games get 3 GiB plus 64 MiB per mod, up to 16 GiB.
+/// About 2 GiB of the system memory is left for the OS and the JVM itself.
+pub fn auto_max_heap(total_memory: Option<u64>, mod_count: usize) -> u64 {
+    let wanted = if mod_count == 0 {
+        2048
+    } else {
+        3072 + 64 * mod_count as u64
+    }.min(MAX_AUTO_HEAP);
+    match total_memory {
+        Some(total) => wanted.min(total.saturating_sub(2048).max(1024)),
+        None => wanted
+    }
+}
+
+/// Get the arguments from [MemorySettings] and [GCPreset].
+/// Mods in `mods_dir` are counted in auto mode, and the GC preset is chosen for the Java with `java_major_version`.
+pub(crate) fn get_tuning_args(memory: &MemorySettings, gc_preset: Option<GCPreset>, mods_dir: &BetterPath, java_major_version: usize) -> Vec<OsString> {
+    let mod_count = if memory.auto {
+        std::fs::read_dir(mods_dir).into_iter().flatten().flatten()
+            .filter(|v|v.file_name().to_string_lossy().ends_with(".jar"))
+            .count()
+    } else {
+        0
+    };
+    let mut args = memory.to_args(mod_count);
+    if let Some(preset) = gc_preset {
+        args.extend(preset.to_args(java_major_version.max(8)));
+    }
+    args
+}
+
+/// Named garbage collector presets.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum GCPreset {
+    /// G1 with the flags used by the official launcher.
+    G1Tuned,
+    /// Generational ZGC. It requires Java 21, so [Self::G1Tuned] is used for older Java.
+    ZgcGenerational,
+    /// Aikar's flags, tuned G1 for servers and modpacks.
+    Aikar
+}
+
+impl GCPreset {
+    /// Get the JVM arguments for a Java with `java_major_version`.
+    pub fn to_args(&self, java_major_version: usize) -> Vec<OsString> {
+        let args: &[&str] = match self {
+            GCPreset::ZgcGenerational if java_major_version >= 21 => &[
+                "-XX:+UseZGC", "-XX:+ZGenerational"
+            ],
+            GCPreset::G1Tuned | GCPreset::ZgcGenerational => &[
+                "-XX:+UnlockExperimentalVMOptions", "-XX:+UseG1GC", "-XX:G1NewSizePercent=20",
+                "-XX:G1ReservePercent=20", "-XX:MaxGCPauseMillis=50", "-XX:G1HeapRegionSize=32M"
+            ],
+            GCPreset::Aikar => &[
+                "-XX:+UseG1GC", "-XX:+ParallelRefProcEnabled", "-XX:MaxGCPauseMillis=200",
+                "-XX:+UnlockExperimentalVMOptions", "-XX:+DisableExplicitGC", "-XX:+AlwaysPreTouch",
+                "-XX:G1NewSizePercent=30", "-XX:G1MaxNewSizePercent=40", "-XX:G1HeapRegionSize=8M",
+                "-XX:G1ReservePercent=20", "-XX:G1HeapWastePercent=5", "-XX:G1MixedGCCountTarget=4",
+                "-XX:InitiatingHeapOccupancyPercent=15", "-XX:G1MixedGCLiveThresholdPercent=90",
+                "-XX:G1RSetUpdatingPauseTimePercent=5", "-XX:SurvivorRatio=32", "-XX:+PerfDisableSharedMem",
+                "-XX:MaxTenuringThreshold=1"
+            ]
+        };
+        args.iter().map(OsString::from).collect()
+    }
+}
@@ -16,11 +16,11 @@ use futures_util::StreamExt;
 use map_macro::hash_map_e;
 #[cfg(feature="msa_auth")]
 use minecraft::login::microsoft::MicrosoftAccountConstructor;
-use minecraft::{login::{yggdrasil::{ali::AuthlibInjectorAccountConstructor, mul::MinecraftUniversalLoginAccountConstructor}, AccountConstructor, OfflineAccountConstructor}, schemas::VersionJSON, version::MinecraftInstallation};
+use minecraft::{login::{yggdrasil::{ali::AuthlibInjectorAccountConstructor, mul::MinecraftUniversalLoginAccountConstructor}, AccountConstructor, OfflineAccountConstructor}, schemas::{Library, VersionJSON}, version::MinecraftInstallation};
 use reqwest::Client;
 use tokio::{fs::{self, create_dir_all}, io::AsyncWriteExt};
 use tokio_util::codec::{FramedRead, LinesCodec};
-use utils::{osstr_concat, BetterPath};
+use utils::{mirror::IdentityMirrorProvider, osstr_concat, BetterPath, MirrorProvider};
 
 use crate::utils::merge_version_json;
 #[macro_use]
@@ -29,6 +29,7 @@ extern crate rust_i18n;
 #[cfg(feature="content_services")]
 pub mod content_services;
 pub mod minecraft;
+pub mod java;
 pub mod utils;
 pub mod components;
 
@@ -59,8 +60,13 @@ pub struct LauncherContext {
     pub download_threads_per_file: u16,
     /// Max parallel downloading files.
     pub download_parallel_files: usize,
-    /// BMCLAPI mirror.
-    pub bmclapi_mirror: Option<String>
+    /// Maps URLs to mirrors for all downloads and requests. It uses no mirror by default.
+    /// See [utils::mirror::BMCLAPIMirrorProvider] for BMCLAPI.
+    pub mirror_provider: Box<dyn MirrorProvider>,
+    /// Libraries that replace the ones in version JSONs on this platform, like arm64 LWJGL natives.
+    /// The keys are library names, with `:natives` appended for native libraries. [None] removes the library.
+    /// See [Self::load_library_overrides].
+    pub library_overrides: HashMap<String, Option<Library>>
 }
 
 /// A trait for interacting with users that should be implemented by the client.
@@ -212,11 +218,32 @@ impl LauncherContext {
             download_retries: 5,
             download_threads_per_file: 8,
             download_parallel_files: 8,
-            bmclapi_mirror: None
+            mirror_provider: Box::new(IdentityMirrorProvider),
+            library_overrides: HashMap::new()
         };
         Ok(ctx)
     }
     
+    /// Load [Self::library_overrides] from a JSON table like HMCL's `natives.json`.
+    /// The top level keys are platforms like `linux-arm64`, see [utils::get_platform].
+    /// Only the overrides for current platform are loaded.
+    pub fn load_library_overrides(&mut self, json: &str) -> Result<()> {
+        let mut table: HashMap<String, HashMap<String, Option<Library>>> = serde_json::from_str(json)?;
+        if let Some(overrides) = table.remove(&utils::get_platform()) {
+            self.library_overrides.extend(overrides);
+        }
+        Ok(())
+    }
+
+    pub(crate) fn apply_library_overrides(&self, libraries: &[Library]) -> Vec<Library> {
+        libraries.iter()
+            .filter_map(|v|match self.library_overrides.get(&v.get_override_key()) {
+                Some(replacement) => replacement.clone(),
+                None => Some(v.clone())
+            })
+            .collect()
+    }
+
     /// List the names of minecraft installations in the `root_path`.
     pub async fn list_installations(&self) -> Result<Vec<String>> {
         let version_dir = &*(&self.root_path / "versions");
@@ -1,7 +1,17 @@
 //! Things about Minecraft itself.
 
+pub mod import;
 pub mod install;
+pub mod crash;
+pub mod garbage;
+pub mod instance;
 pub mod schemas;
+pub mod verify;
 pub mod version;
 pub mod launch;
 pub mod login;
+pub mod logging;
+pub mod plan;
+pub mod process;
+pub mod script;
+pub mod server;
@@ -0,0 +1,296 @@
+//! Things about analyzing crashes.
+
+use std::{fmt::Display, time::SystemTime};
+
+use anyhow::Result;
+use tokio::fs;
+
+#[cfg(feature="mod_loaders")]
+use crate::components::mods::{ModIssue, ModIssueLevel};
+use crate::utils::{parse_java_major_version, BetterPath};
+
+use super::version::MinecraftInstallation;
+
+/// A section in a [CrashReport], like `-- System Details --`.
+#[derive(Debug, Clone)]
+pub struct CrashReportSection {
+    /// The title, like `System Details`.
+    pub title: String,
+    /// The key-value pairs under `Details:`.
+    pub details: Vec<(String, String)>,
+    /// The raw content of this section.
+    pub body: String
+}
+
+/// A parsed Minecraft crash report.
+#[derive(Debug, Clone)]
+pub struct CrashReport {
+    /// The path of the report.
+    pub path: BetterPath,
+    /// The description, like `Rendering overlay`.
+    pub description: Option<String>,
+    /// The stack trace at the head of the report.
+    pub stacktrace: String,
+    /// The sections.
+    pub sections: Vec<CrashReportSection>,
+    /// The whole content.
+    pub content: String
+}
+
+impl CrashReport {
+    /// Parse a crash report.
+    pub fn parse(path: BetterPath, content: String) -> Self {
+        let mut description = None;
+        let mut stacktrace = vec![];
+        let mut sections: Vec<CrashReportSection> = vec![];
+        let mut in_stacktrace = false;
+        for line in content.lines() {
+            if let Some(title) = line.strip_prefix("-- ").and_then(|v|v.strip_suffix(" --")) {
+                in_stacktrace = false;
+                sections.push(CrashReportSection {
+                    title: title.to_string(),
+                    details: vec![],
+                    body: String::new()
+                });
+                continue;
+            }
+            if let Some(section) = sections.last_mut() {
+                section.body.push_str(line);
+                section.body.push('\n');
+                if line.starts_with('\t') && !line.starts_with("\tat ") && let Some((k, v)) = line.trim().split_once(": ") {
+                    section.details.push((k.to_string(), v.to_string()));
+                }
+            } else if let Some(desc) = line.strip_prefix("Description: ") {
+                description = Some(desc.to_string());
+                in_stacktrace = true;
+            } else if line.starts_with("A detailed walkthrough of the error") {
+                in_stacktrace = false;
+            } else if in_stacktrace && !(stacktrace.is_empty() && line.is_empty()) {
+                stacktrace.push(line);
+            }
+        }
+        Self {
+            path,
+            description,
+            stacktrace: stacktrace.join("\n").trim_end().to_string(),
+            sections,
+            content
+        }
+    }
+
+    /// Get a detail value by key in any section, like `Java Version`.
+    pub fn get_detail(&self, key: &str) -> Option<&str> {
+        self.sections.iter()
+            .flat_map(|v|v.details.iter())
+            .find(|(k, _)|k == key)
+            .map(|(_, v)|v.as_str())
+    }
+}
+
+/// A known cause of a crash.
+#[derive(Debug)]
+pub enum CrashCause {
+    /// A mod dependency issue found by [MinecraftInstallation::check_mod_dependencies].
+    #[cfg(feature="mod_loaders")]
+    ModDependency(ModIssue),
+    /// The Java version doesn't match [super::schemas::JavaInfo::major_version].
+    WrongJava {
+        /// The required major version.
+        required: usize,
+        /// The major version of the Java that was used, if known.
+        current: Option<usize>
+    },
+    /// The JVM ran out of memory.
+    OutOfMemory,
+    /// A mixin failed to apply.
+    MixinFailure {
+        /// The mod that provides the mixin, if known.
+        mod_id: Option<String>,
+        /// The mixin config, if known.
+        config: Option<String>
+    },
+    /// A mod is installed more than once.
+    DuplicateMod {
+        /// The mod id, if known.
+        mod_id: Option<String>,
+        /// The files that contain the mod, if known.
+        files: Vec<String>
+    },
+    /// The JVM itself crashed.
+    JvmCrash {
+        /// The problematic frame, if known.
+        problematic_frame: Option<String>
+    }
+}
+
+impl Display for CrashCause {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let unknown = t!("crash.unknown");
+        match self {
+            #[cfg(feature="mod_loaders")]
+            CrashCause::ModDependency(issue) => write!(f, "{}", issue.message),
+            CrashCause::WrongJava { required, current } => write!(f, "{}", t!("crash.wrong_java",
+                required = required, current = current.map_or(unknown.to_string(), |v|v.to_string()))),
+            CrashCause::OutOfMemory => write!(f, "{}", t!("crash.out_of_memory")),
+            CrashCause::MixinFailure { mod_id, config } => write!(f, "{}", t!("crash.mixin_failure",
+                mod_id = mod_id.as_deref().unwrap_or(&unknown), config = config.as_deref().unwrap_or(&unknown))),
+            CrashCause::DuplicateMod { mod_id, files } => write!(f, "{}", t!("crash.duplicate_mod",
+                mod_id = mod_id.as_deref().unwrap_or(&unknown), files = files.join(", "))),
+            CrashCause::JvmCrash { problematic_frame } => write!(f, "{}", t!("crash.jvm_crash",
+                frame = problematic_frame.as_deref().unwrap_or(&unknown)))
+        }
+    }
+}
+
+/// The result of [MinecraftInstallation::analyze_crash].
+#[derive(Debug)]
+pub struct CrashAnalysis {
+    /// The newest crash report.
+    pub crash_report: Option<CrashReport>,
+    /// The newest `hs_err_pid*.log`.
+    pub jvm_crash_log: Option<BetterPath>,
+    /// The known causes.
+    pub causes: Vec<CrashCause>
+}
+
+async fn find_newest(dir: &BetterPath, filter: impl Fn(&str) -> bool, since: Option<SystemTime>) -> Result<Option<BetterPath>> {
+    let mut newest: Option<(SystemTime, BetterPath)> = None;
+    let Ok(mut entries) = fs::read_dir(dir).await else {
+        return Ok(None);
+    };
+    while let Some(entry) = entries.next_entry().await? {
+        if !entry.file_type().await?.is_file() || !filter(&entry.file_name().to_string_lossy()) {
+            continue;
+        }
+        let modified = entry.metadata().await?.modified()?;
+        if since.is_some_and(|since|modified < since) {
+            continue;
+        }
+        if newest.as_ref().is_none_or(|(time, _)|modified > *time) {
+            newest = Some((modified, BetterPath(entry.path())));
+        }
+    }
+    Ok(newest.map(|(_, path)|path))
+}
+
+fn between<'s>(text: &'s str, start: &str, end: &[char]) -> Option<&'s str> {
+    let begin = text.find(start)? + start.len();
+    let rest = &text[begin..];
+    Some(&rest[..rest.find(end).unwrap_or(rest.len())])
+}
+
+fn find_mixin_failure(text: &str) -> Option<CrashCause> {
+    for line in text.lines() {
+        if let Some(mod_id) = between(line, "Mixin apply for mod ", &[' ']) {
+            return Some(CrashCause::MixinFailure {
+                mod_id: Some(mod_id.to_string()),
+                config: between(line, "failed ", &[':', ' ']).map(str::to_string)
+            });
+        }
+        if line.contains("Mixin [") && (line.contains("FAILED") || line.contains("failed")) {
+            return Some(CrashCause::MixinFailure {
+                mod_id: between(line, " from mod ", &[']', ' ']).map(str::to_string),
+                config: between(line, "in config [", &[']']).map(str::to_string)
+            });
+        }
+    }
+    if text.contains("MixinApplyError") || text.contains("MixinTransformerError") {
+        return Some(CrashCause::MixinFailure { mod_id: None, config: None });
+    }
+    None
+}
+
+fn find_duplicate_mods(text: &str, causes: &mut Vec<CrashCause>) {
+    for line in text.lines() {
+        // Forge and NeoForge: `Mod ID: 'jei' from mod files: a.jar, b.jar`
+        if let Some(mod_id) = between(line, "Mod ID: '", &['\'']) && let Some(files) = between(line, "from mod files: ", &[]) {
+            causes.push(CrashCause::DuplicateMod {
+                mod_id: Some(mod_id.to_string()),
+                files: files.split(", ").map(str::to_string).collect()
+            });
+        }
+    }
+    if causes.iter().all(|v|!matches!(v, CrashCause::DuplicateMod { .. }))
+        && (text.contains("DuplicateModsFoundException") || text.to_ascii_lowercase().contains("duplicate mods")) {
+        causes.push(CrashCause::DuplicateMod { mod_id: None, files: vec![] });
+    }
+}
+
+fn find_wrong_java(text: &str, required: usize, current: Option<usize>) -> Option<CrashCause> {
+    if text.contains("UnsupportedClassVersionError") {
+        // Class file version = Java version + 44
+        let required = between(text, "class file version ", &['.', ' ']).and_then(|v|v.parse::<usize>().ok()).and_then(|v|v.checked_sub(44));
+        let current = between(text, "class file versions up to ", &['.', ' ']).and_then(|v|v.parse::<usize>().ok()).and_then(|v|v.checked_sub(44));
+        if let Some(required) = required {
+            return Some(CrashCause::WrongJava { required, current });
+        }
+    }
+    if required == 0 {
+        return None;
+    }
+    match current {
+        Some(current) if current < required => Some(CrashCause::WrongJava { required, current: Some(current) }),
+        // Old versions crash on Java 9+ because the system class loader isn't an URLClassLoader anymore.
+        Some(current) if required <= 8 && current > 8 && text.contains("URLClassLoader") => Some(CrashCause::WrongJava { required, current: Some(current) }),
+        _ => None
+    }
+}
+
+impl MinecraftInstallation<'_> {
+    /// Analyze the crash of the game.
+    /// It reads the newest crash report, `hs_err_pid*.log` and `logs/latest.log`, then matches known causes.
+    ///
+    /// # Arguments
+    /// * `since` - Ignore the files older than it. Usually the time when the game was launched.
+    pub async fn analyze_crash(&self, since: Option<SystemTime>) -> Result<CrashAnalysis> {
+        let cwd = &self.version_launch_work_dir;
+        let crash_report = if let Some(path) = find_newest(&(cwd / "crash-reports"), |v|v.starts_with("crash-") && v.ends_with(".txt"), since).await? {
+            let content = String::from_utf8_lossy(&fs::read(&path).await?).to_string();
+            Some(CrashReport::parse(path, content))
+        } else {
+            None
+        };
+        let jvm_crash_log = find_newest(cwd, |v|v.starts_with("hs_err_pid") && v.ends_with(".log"), since).await?;
+        let jvm_crash_content = if let Some(path) = &jvm_crash_log {
+            Some(String::from_utf8_lossy(&fs::read(path).await?).to_string())
+        } else {
+            None
+        };
+        let latest_log = if let Some(path) = find_newest(&(cwd / "logs"), |v|v == "latest.log", since).await? {
+            String::from_utf8_lossy(&fs::read(&path).await?).to_string()
+        } else {
+            String::new()
+        };
+        let text = [crash_report.as_ref().map(|v|v.content.as_str()), jvm_crash_content.as_deref(), Some(latest_log.as_str())]
+            .into_iter().flatten().collect::<Vec<_>>().join("\n");
+
+        let mut causes = vec![];
+        let current_java = crash_report.as_ref().and_then(|v|v.get_detail("Java Version"))
+            .or_else(||jvm_crash_content.as_deref().and_then(|v|between(v, "# JRE version: ", &['\n'])).and_then(|v|between(v, "(", &[')'])))
+            .and_then(parse_java_major_version);
+        if let Some(cause) = find_wrong_java(&text, self.obj.get_base().java_version.major_version, current_java) {
+            causes.push(cause);
+        }
+        if text.contains("java.lang.OutOfMemoryError") || text.contains("There is insufficient memory for the Java Runtime Environment") {
+            causes.push(CrashCause::OutOfMemory);
+        }
+        if let Some(cause) = find_mixin_failure(&text) {
+            causes.push(cause);
+        }
+        find_duplicate_mods(&text, &mut causes);
+        #[cfg(feature="mod_loaders")]
+        if !self.extra_data.components.is_empty() && let Ok(issues) = self.check_mod_dependencies().await {
+            causes.extend(issues.into_iter().filter(|v|v.level == ModIssueLevel::Hard).map(CrashCause::ModDependency));
+        }
+        if let Some(content) = &jvm_crash_content && !causes.iter().any(|v|matches!(v, CrashCause::OutOfMemory)) {
+            causes.push(CrashCause::JvmCrash {
+                problematic_frame: between(content, "# Problematic frame:\n# ", &['\n']).map(str::to_string)
+            });
+        }
+        Ok(CrashAnalysis {
+            crash_report,
+            jvm_crash_log,
+            causes
+        })
+    }
+}
@@ -0,0 +1,147 @@
+//! Things about cleaning up unused libraries and assets.
+
+use std::{collections::HashSet, path::{Path, PathBuf}};
+
+use anyhow::{anyhow, Ok, Result};
+use tokio::fs;
+
+use crate::{utils::BetterPath, LauncherContext};
+
+use super::{server::ServerLaunchTarget, version::MinecraftInstallation};
+
+/// The result of [LauncherContext::collect_garbage].
+#[derive(Debug, Clone, Default)]
+pub struct GarbageReport {
+    /// Files that no installation uses, and their sizes in bytes.
+    pub orphans: Vec<(BetterPath, u64)>,
+    /// The total size of [Self::orphans] in bytes.
+    pub total_size: u64,
+    /// Whether `assets/objects` is skipped, because the assets index of some installation isn't downloaded.
+    pub assets_skipped: bool,
+    /// Whether [Self::orphans] are deleted.
+    pub deleted: bool
+}
+
+/// List all files in `dir` with their sizes.
+pub(crate) async fn list_files(dir: &BetterPath) -> Result<Vec<(PathBuf, u64)>> {
+    let mut ret = vec![];
+    let mut stack = vec![dir.0.clone()];
+    while let Some(dir) = stack.pop() {
+        let Result::Ok(mut entries) = fs::read_dir(&dir).await else {
+            continue;
+        };
+        while let Some(entry) = entries.next_entry().await? {
+            let meta = entry.metadata().await?;
+            if meta.is_dir() {
+                stack.push(entry.path());
+            } else {
+                ret.push((entry.path(), meta.len()));
+            }
+        }
+    }
+    Ok(ret)
+}
+
+/// Guess the directories of the files that components of `mc` generated, if they aren't recorded in [super::version::DMCLCExtraData::generated_libraries].
+/// Forge and NeoForge write them beside their own libraries, and to `net/minecraft/client/<version>-<MCP version>`.
+fn guess_generated_dirs(mc: &MinecraftInstallation, libraries: &BetterPath) -> Result<Vec<PathBuf>> {
+    let mut ret: Vec<PathBuf> = mc.get_files(false, false, None)?.into_iter()
+        .filter(|(_, path)|path.0.starts_with(&libraries.0))
+        .filter_map(|(_, path)|path.0.parent().map(Path::to_path_buf))
+        .collect();
+    if let Some(version) = &mc.extra_data.version && let Result::Ok(entries) = std::fs::read_dir(libraries.0.join("net/minecraft/client")) {
+        let prefix = format!("{version}-");
+        ret.extend(entries.flatten().filter(|v|v.file_name().to_string_lossy().starts_with(&prefix)).map(|v|v.path()));
+    }
+    Ok(ret)
+}
+
+/// Remove empty directories in `dir`, but keep `dir` itself.
+async fn remove_empty_dirs(dir: &BetterPath) -> Result<()> {
+    let mut dirs = vec![];
+    let mut stack = vec![dir.0.clone()];
+    while let Some(dir) = stack.pop() {
+        let Result::Ok(mut entries) = fs::read_dir(&dir).await else {
+            continue;
+        };
+        while let Some(entry) = entries.next_entry().await? {
+            if entry.file_type().await?.is_dir() {
+                stack.push(entry.path());
+                dirs.push(entry.path());
+            }
+        }
+    }
+    // Children are after their parents, so they're removed first.
+    for dir in dirs.iter().rev() {
+        // It fails if the directory isn't empty.
+        let _ = fs::remove_dir(dir).await;
+    }
+    Ok(())
+}
+
+impl LauncherContext {
+    /// Find the files in `libraries`, `assets/objects` and `assets/indexes` that no installation or server uses.
+    /// Files generated by components, like the patched jars of Forge, are used if they're in [super::version::DMCLCExtraData::generated_libraries].
+    /// For installations that don't record them, the directories they may be in are kept.
+    /// They're deleted unless `dry_run` is true.
+    /// It fails if an installation can't be read, as its files are unknown.
+    pub async fn collect_garbage(&self, dry_run: bool) -> Result<GarbageReport> {
+        let libraries = *(&self.root_path / "libraries");
+        let mut used = HashSet::new();
+        let mut kept_dirs = vec![];
+        let mut assets_skipped = false;
+        for name in self.list_installations().await? {
+            let mc = self.get_installation(&name).await
+                .ok_or(anyhow!(t!("garbage.broken_installation", name = name)))?;
+            let index = mc.read_assets_index().await?;
+            if index.is_none() {
+                assets_skipped = true;
+            }
+            used.insert(mc.get_assets_index_path().0);
+            used.extend(mc.get_files(false, false, index.as_ref())?.into_iter().map(|(_, path)|path.0));
+            used.extend(mc.gen_classpath().into_iter().map(PathBuf::from));
+            used.extend(mc.get_native_archives().into_iter().map(|v|v.0));
+            match &mc.extra_data.generated_libraries {
+                Some(files) => used.extend(files.iter().map(|v|libraries.0.join(v))),
+                None if !mc.extra_data.components.is_empty() => kept_dirs.extend(guess_generated_dirs(&mc, &libraries)?),
+                None => ()
+            }
+        }
+        for name in self.list_servers().await? {
+            let server = self.get_server(&name).await
+                .ok_or(anyhow!(t!("garbage.broken_installation", name = name)))?;
+            if let ServerLaunchTarget::MainClass { classpath, .. } = &server.extra_data.launch_target {
+                used.extend(classpath.iter().map(PathBuf::from));
+            }
+        }
+
+        let objects = *(&self.root_path / "assets/objects");
+        let indexes = *(&self.root_path / "assets/indexes");
+        let mut files = list_files(&libraries).await?;
+        files.extend(list_files(&indexes).await?);
+        if !assets_skipped {
+            files.extend(list_files(&objects).await?);
+        }
+        let mut report = GarbageReport {
+            assets_skipped,
+            deleted: !dry_run,
+            ..Default::default()
+        };
+        for (path, size) in files {
+            if used.contains(&path) || path.file_name().is_some_and(|v|v == "CACHEDIR.TAG")
+                || kept_dirs.iter().any(|v|path.starts_with(v)) {
+                continue;
+            }
+            report.total_size += size;
+            report.orphans.push((BetterPath(path), size));
+        }
+        if !dry_run {
+            for (path, _) in &report.orphans {
+                fs::remove_file(path).await?;
+            }
+            remove_empty_dirs(&libraries).await?;
+            remove_empty_dirs(&objects).await?;
+        }
+        Ok(report)
+    }
+}
@@ -0,0 +1,443 @@
+//! Things about importing installations from other launchers.
+
+use std::{collections::HashMap, ffi::OsString, path::{Path, PathBuf}};
+
+use anyhow::{anyhow, Ok, Result};
+use serde::Deserialize;
+use serde_json::Value;
+use tokio::fs;
+
+use crate::{utils::{check_cancelled, split_command_line, BetterPath, ProgressSink}, LauncherContext};
+
+use super::{instance::{check_name, copy_dir, remove_on_error, rename_version_files, InstanceMetadata, SKIPPED_DIRS}, version::MinecraftInstallation};
+
+/// Entries of a shared game dir that are copied into the imported installation.
+const GAME_DIR_ENTRIES: [&str; 12] = [
+    "saves", "mods", "resourcepacks", "shaderpacks", "config", "defaultconfigs", "screenshots",
+    "schematics", "options.txt", "optionsof.txt", "optionsshaders.txt", "servers.dat"
+];
+
+/// Components in `mmc-pack.json` and the names of their [crate::components::install::ComponentInstaller]s.
+const MULTIMC_COMPONENTS: [(&str, &str); 4] = [
+    ("net.minecraftforge", "forge"),
+    ("net.neoforged", "neoforge"),
+    ("net.fabricmc.fabric-loader", "fabric"),
+    ("org.quiltmc.quilt-loader", "quilt")
+];
+
+/// Components in `mmc-pack.json` that are installed with Minecraft or the loaders.
+const MULTIMC_BUILTIN_COMPONENTS: [&str; 5] = ["net.minecraft", "org.lwjgl", "org.lwjgl3", "net.fabricmc.intermediary", "org.quiltmc.hashed"];
+
+/// Where the game files of an imported installation come from.
+enum GameDir {
+    /// The version dir already has them.
+    Version,
+    /// They're in a shared game dir, so they're copied.
+    Shared(BetterPath),
+    /// They're in the shared game dir of the other launcher, which other imported installations use too.
+    /// They're copied to the shared game dir of this launcher unless they exist there, and the installation uses it.
+    Root(BetterPath)
+}
+
+/// Settings from another launcher, applied to [super::version::DMCLCExtraData].
+#[derive(Default)]
+struct ImportedSettings {
+    java: Option<String>,
+    jvm_arguments: Option<String>,
+    game_arguments: Option<String>,
+    auto_memory: bool,
+    min_memory: Option<u64>,
+    max_memory: Option<u64>,
+    before_command: Option<String>,
+    wrapper_command: Option<String>,
+    after_command: Option<String>
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct LauncherProfile {
+    name: Option<String>,
+    #[serde(rename = "type")]
+    typ: Option<String>,
+    last_version_id: Option<String>,
+    game_dir: Option<String>,
+    java_dir: Option<String>,
+    java_args: Option<String>,
+    icon: Option<String>
+}
+
+#[derive(Deserialize)]
+struct LauncherProfiles {
+    profiles: HashMap<String, LauncherProfile>
+}
+
+#[derive(Deserialize)]
+struct MultiMCComponent {
+    uid: String,
+    version: Option<String>
+}
+
+#[derive(Deserialize)]
+struct MultiMCPack {
+    components: Vec<MultiMCComponent>
+}
+
+/// Parse a heap size argument like `-Xmx4G`. Returns whether it's the max heap size, and the size in MiB.
+/// Sizes smaller than 1 MiB are rounded up, and it returns [None] for 0.
+fn parse_memory_arg(arg: &str) -> Option<(bool, u64)> {
+    let (is_max, size) = if let Some(size) = arg.strip_prefix("-Xmx") {
+        (true, size)
+    } else {
+        (false, arg.strip_prefix("-Xms")?)
+    };
+    let unit = size.chars().last()?;
+    let size = if unit.is_ascii_digit() {
+        size.parse::<u64>().ok()?.div_ceil(1024 * 1024)
+    } else {
+        let value = size.strip_suffix(unit)?.parse::<u64>().ok()?;
+        match unit.to_ascii_lowercase() {
+            'k' => value.div_ceil(1024),
+            'm' => value,
+            'g' => value * 1024,
+            't' => value * 1024 * 1024,
+            _ => return None
+        }
+    };
+    (size != 0).then_some((is_max, size))
+}
+
+fn parse_ini(text: &str) -> HashMap<String, String> {
+    text.lines()
+        .filter(|v|!v.starts_with('[') && !v.starts_with('#') && !v.starts_with(';'))
+        .filter_map(|v|v.split_once('='))
+        .map(|(k, v)|(k.trim().to_string(), v.trim().trim_matches('"').replace("\\n", "\n")))
+        .collect()
+}
+
+fn non_empty(value: Option<&String>) -> Option<String> {
+    value.filter(|v|!v.is_empty()).cloned()
+}
+
+async fn copy_entry(from: &BetterPath, to: &BetterPath) -> Result<()> {
+    if from.0.is_dir() {
+        copy_dir(from, to, &[]).await
+    } else {
+        fs::copy(from, to).await?;
+        Ok(())
+    }
+}
+
+impl ImportedSettings {
+    /// Read HMCL's `hmclversion.cfg`. Global settings aren't imported.
+    fn from_hmcl(cfg: &Value, src_root: &BetterPath) -> (Self, GameDir) {
+        let str = |key: &str|cfg[key].as_str().filter(|v|!v.is_empty()).map(str::to_string);
+        let game_dir = match cfg["gameDirType"].as_u64() {
+            Some(1) => GameDir::Version,
+            Some(2) if let Some(dir) = str("gameDir") => GameDir::Shared(BetterPath(PathBuf::from(dir))),
+            _ => GameDir::Shared(src_root.clone())
+        };
+        let settings = ImportedSettings {
+            java: str("javaDir"),
+            jvm_arguments: str("javaArgs"),
+            game_arguments: str("minecraftArgs"),
+            auto_memory: cfg["autoMemory"].as_bool().unwrap_or(false),
+            min_memory: cfg["minMemory"].as_u64(),
+            max_memory: cfg["maxMemory"].as_u64(),
+            before_command: str("precalledCommand"),
+            wrapper_command: str("wrapper"),
+            after_command: None
+        };
+        (settings, game_dir)
+    }
+
+    /// Read MultiMC's `instance.cfg`. Only the overridden settings are imported.
+    fn from_multimc(cfg: &HashMap<String, String>) -> Self {
+        let enabled = |key: &str|cfg.get(key).is_some_and(|v|v == "true");
+        let mut settings = ImportedSettings::default();
+        if enabled("OverrideJavaLocation") {
+            settings.java = non_empty(cfg.get("JavaPath"));
+        }
+        if enabled("OverrideJavaArgs") {
+            settings.jvm_arguments = non_empty(cfg.get("JvmArgs"));
+        }
+        if enabled("OverrideMemory") {
+            settings.min_memory = cfg.get("MinMemAlloc").and_then(|v|v.parse().ok());
+            settings.max_memory = cfg.get("MaxMemAlloc").and_then(|v|v.parse().ok());
+        }
+        if enabled("OverrideCommands") {
+            settings.before_command = non_empty(cfg.get("PreLaunchCommand"));
+            settings.wrapper_command = non_empty(cfg.get("WrapperCommand"));
+            settings.after_command = non_empty(cfg.get("PostExitCommand"));
+        }
+        settings
+    }
+
+    fn apply(self, mc: &mut MinecraftInstallation) {
+        let extra = &mut mc.extra_data;
+        extra.with_java = self.java.or(extra.with_java.take());
+        extra.memory.auto |= self.auto_memory;
+        extra.memory.min = self.min_memory.or(extra.memory.min);
+        extra.memory.max = self.max_memory.or(extra.memory.max);
+        let mut jvm_arguments = vec![];
+        for arg in self.jvm_arguments.as_deref().and_then(split_command_line).unwrap_or_default() {
+            // Heap sizes are moved to the memory settings, unless they're set already.
+            match parse_memory_arg(&arg) {
+                Some((true, size)) if self.max_memory.is_none() => extra.memory.max = Some(size),
+                Some((false, size)) if self.min_memory.is_none() => extra.memory.min = Some(size),
+                _ => jvm_arguments.push(OsString::from(arg))
+            }
+        }
+        if !jvm_arguments.is_empty() {
+            extra.extra_jvm_arguments.get_or_insert_default().extend(jvm_arguments);
+        }
+        if let Some(args) = self.game_arguments.as_deref().and_then(split_command_line) && !args.is_empty() {
+            extra.extra_game_arguments.get_or_insert_default().extend(args.into_iter().map(OsString::from));
+        }
+        extra.before_command = self.before_command.or(extra.before_command.take());
+        extra.wrapper_command = self.wrapper_command.or(extra.wrapper_command.take());
+        extra.after_command = self.after_command.or(extra.after_command.take());
+    }
+}
+
+impl LauncherContext {
+    /// Make a valid and unused installation name from `base`.
+    async fn unique_name(&self, base: &str) -> Result<String> {
+        let base: String = base.trim().chars()
+            .map(|c|if c.is_control() || r#"/\:*?"<>|"#.contains(c) { '_' } else { c })
+            .collect();
+        let base = if check_name(&base).is_ok() { base } else { "imported".to_string() };
+        let mut name = base.clone();
+        let mut index = 2;
+        while fs::try_exists(&self.root_path / "versions" / &name).await? {
+            name = format!("{base}-{index}");
+            index += 1;
+        }
+        Ok(name)
+    }
+
+    /// Copy the game files from `game_dir` into the version dir of `mc`, and enable the independent game dir.
+    /// For [GameDir::Root], they're copied to the shared game dir instead, and the independent game dir is disabled.
+    async fn import_game_dir(&self, mc: &mut MinecraftInstallation<'_>, game_dir: GameDir) -> Result<()> {
+        let (from_dir, to_dir, independent) = match game_dir {
+            GameDir::Version => (None, &mc.version_root, true),
+            GameDir::Shared(dir) => (Some(dir), &mc.version_root, true),
+            GameDir::Root(dir) => (Some(dir), &self.root_path, false)
+        };
+        if let Some(from_dir) = from_dir {
+            for entry in GAME_DIR_ENTRIES {
+                let from = *(&from_dir / entry);
+                let to = *(to_dir / entry);
+                if fs::try_exists(&from).await? && (independent || !fs::try_exists(&to).await?) {
+                    copy_entry(&from, &to).await?;
+                }
+            }
+        }
+        mc.extra_data.independent_game_dir = independent;
+        Ok(())
+    }
+
+    async fn import_version_with(
+        &self, src_root: &BetterPath, version: &str, name: &str,
+        settings: Option<(ImportedSettings, GameDir)>, metadata: InstanceMetadata
+    ) -> Result<MinecraftInstallation<'_>> {
+        let src_dir = *(src_root / "versions" / version);
+        let Some(json) = fs::read(&src_dir / format!("{version}.json")).await.ok()
+            .and_then(|v|serde_json::from_slice::<Value>(&v).ok()) else {
+            return Err(anyhow!(t!("import.version_not_found", version = version, path = src_root.0.display())));
+        };
+        let dir = self.check_new_name(name).await?;
+        let result = self.copy_imported_version(src_root, version, name, json, settings, metadata).await;
+        remove_on_error(&dir, result).await
+    }
+
+    async fn copy_imported_version(
+        &self, src_root: &BetterPath, version: &str, name: &str, json: Value,
+        settings: Option<(ImportedSettings, GameDir)>, metadata: InstanceMetadata
+    ) -> Result<MinecraftInstallation<'_>> {
+        let src_versions = *(src_root / "versions");
+        let src_dir = *(&src_versions / version);
+        let dir = *(&self.root_path / "versions" / name);
+
+        // Copy the versions it inherits from, unless they exist here.
+        let mut parent = json["inheritsFrom"].as_str().map(str::to_string);
+        while let Some(current) = parent.take() {
+            let from = *(&src_versions / &current);
+            let to = *(&self.root_path / "versions" / &current);
+            if fs::try_exists(&to).await? || !fs::try_exists(&from).await? {
+                break;
+            }
+            copy_dir(&from, &to, &SKIPPED_DIRS).await?;
+            parent = fs::read(&to / format!("{current}.json")).await.ok()
+                .and_then(|v|serde_json::from_slice::<Value>(&v).ok())
+                .and_then(|v|v["inheritsFrom"].as_str().map(str::to_string));
+        }
+
+        let natives = format!("{version}-natives");
+        let mut skip = SKIPPED_DIRS.to_vec();
+        skip.push(&natives);
+        copy_dir(&src_dir, &dir, &skip).await?;
+        rename_version_files(&dir, version, name).await?;
+        let settings = settings.unwrap_or_else(||{
+            let has_game_files = GAME_DIR_ENTRIES.iter().any(|v|(&dir / *v).0.exists());
+            (ImportedSettings::default(), if has_game_files { GameDir::Version } else { GameDir::Shared(src_root.clone()) })
+        });
+
+        let mut mc = self.get_installation(name).await
+            .ok_or(anyhow!(t!("import.version_not_found", version = version, path = src_root.0.display())))?;
+        let lib_root = *(&self.root_path / "libraries");
+        for (_, path) in self.get_library_resources(&mc.obj.get_base().libraries, false)? {
+            let Result::Ok(relative) = path.0.strip_prefix(&lib_root.0) else {
+                continue;
+            };
+            let from = *(src_root / "libraries" / relative.as_os_str());
+            if !path.0.exists() && from.0.is_file() {
+                if let Some(parent) = path.0.parent() {
+                    fs::create_dir_all(parent).await?;
+                }
+                fs::copy(&from, &path).await?;
+            }
+        }
+        let (settings, game_dir) = settings;
+        self.import_game_dir(&mut mc, game_dir).await?;
+        settings.apply(&mut mc);
+        mc.extra_data.metadata = metadata;
+        mc.save().await?;
+        Ok(self.get_installation(name).await.unwrap_or(mc))
+    }
+
+    /// Import a version from the `.minecraft` dir of another launcher, like HMCL, PCL or the official launcher.
+    /// The version is copied as `name`, and the versions it inherits from are copied with their own names if they're missing.
+    /// Libraries are copied if they exist in `minecraft_dir`, and others are downloaded when launching.
+    /// Components are detected by [crate::components::install::ComponentInstaller::find_in_version].
+    /// Settings in HMCL's `hmclversion.cfg` are imported.
+    /// If the version doesn't have its own game dir, saves, mods and so on are copied from the shared one.
+    pub async fn import_version(&self, minecraft_dir: &Path, version: &str, name: &str) -> Result<MinecraftInstallation<'_>> {
+        let src_root = BetterPath(minecraft_dir.to_path_buf());
+        let settings = fs::read(&src_root / "versions" / version / "hmclversion.cfg").await.ok()
+            .and_then(|v|serde_json::from_slice::<Value>(&v).ok())
+            .filter(|v|!v["usesGlobal"].as_bool().unwrap_or(false))
+            .map(|v|ImportedSettings::from_hmcl(&v, &src_root));
+        self.import_version_with(&src_root, version, name, settings, InstanceMetadata::default()).await
+    }
+
+    /// Import the profiles in `launcher_profiles.json` of the official launcher, by [Self::import_version].
+    /// The Java, JVM arguments, game dir and icon of profiles are imported.
+    /// Profiles using the default game dir share the game dir of this launcher, and the files in it are only copied if they don't exist there.
+    /// Profiles whose version isn't installed in `minecraft_dir` are skipped.
+    pub async fn import_launcher_profiles(&self, minecraft_dir: &Path) -> Result<Vec<MinecraftInstallation<'_>>> {
+        let src_root = BetterPath(minecraft_dir.to_path_buf());
+        let profiles: LauncherProfiles = serde_json::from_slice(&fs::read(&src_root / "launcher_profiles.json").await?)?;
+        let mut profiles: Vec<_> = profiles.profiles.into_values().collect();
+        profiles.sort_by(|a, b|a.name.cmp(&b.name));
+        let mut ret = vec![];
+        for profile in profiles {
+            let version = match (profile.typ.as_deref(), profile.last_version_id) {
+                (_, Some(id)) if id != "latest-release" && id != "latest-snapshot" => id,
+                (Some(typ), _) if typ == "latest-release" || typ == "latest-snapshot" => {
+                    let Result::Ok(list) = self.get_version_list().await else {
+                        continue;
+                    };
+                    if typ == "latest-release" { list.latest.release } else { list.latest.snapshot }
+                },
+                _ => continue
+            };
+            if !(&src_root / "versions" / &version / format!("{version}.json")).0.is_file() {
+                continue;
+            }
+            let display_name = profile.name.filter(|v|!v.is_empty());
+            let name = self.unique_name(display_name.as_deref().unwrap_or(&version)).await?;
+            let game_dir = match profile.game_dir.filter(|v|!v.is_empty()) {
+                Some(dir) => GameDir::Shared(BetterPath(PathBuf::from(dir))),
+                None => GameDir::Root(src_root.clone())
+            };
+            let settings = ImportedSettings {
+                java: profile.java_dir.filter(|v|!v.is_empty()),
+                jvm_arguments: profile.java_args,
+                ..Default::default()
+            };
+            let metadata = InstanceMetadata {
+                display_name,
+                icon: profile.icon,
+                ..Default::default()
+            };
+            ret.push(self.import_version_with(&src_root, &version, &name, Some((settings, game_dir)), metadata).await?);
+        }
+        Ok(ret)
+    }
+
+    /// Import a MultiMC or Prism Launcher instance from `instance_dir`, which has `instance.cfg` and `mmc-pack.json`.
+    /// Minecraft and the mod loaders are installed from the network, then the game dir is copied.
+    /// If `name` is [None], the instance name is used.
+    /// Overridden Java, memory, JVM arguments and commands are imported, as well as the icon, notes, group and play time.
+    pub async fn import_multimc_instance(&self, instance_dir: &Path, name: Option<&str>, progress: &dyn ProgressSink) -> Result<MinecraftInstallation<'_>> {
+        let dir = BetterPath(instance_dir.to_path_buf());
+        let invalid = ||anyhow!(t!("import.invalid_instance", path = instance_dir.display()));
+        let cfg = parse_ini(&fs::read_to_string(&dir / "instance.cfg").await.map_err(|_|invalid())?);
+        let pack: MultiMCPack = serde_json::from_slice(&fs::read(&dir / "mmc-pack.json").await.map_err(|_|invalid())?)?;
+        let mc_version = pack.components.iter()
+            .find(|v|v.uid == "net.minecraft")
+            .and_then(|v|v.version.clone())
+            .ok_or_else(invalid)?;
+        let name = match name {
+            Some(name) => name.to_string(),
+            None => self.unique_name(cfg.get("name").map_or(&mc_version, |v|v)).await?
+        };
+        let dir = self.check_new_name(&name).await?;
+        let result = self.install_multimc_instance(instance_dir, &name, &mc_version, &cfg, &pack, progress).await;
+        remove_on_error(&dir, result).await
+    }
+
+    async fn install_multimc_instance(
+        &self, instance_dir: &Path, name: &str, mc_version: &str,
+        cfg: &HashMap<String, String>, pack: &MultiMCPack, progress: &dyn ProgressSink
+    ) -> Result<MinecraftInstallation<'_>> {
+        let dir = BetterPath(instance_dir.to_path_buf());
+        let list = self.get_version_list().await?;
+        let info = list.find_by_id(mc_version)
+            .ok_or(anyhow!(t!("import.version_not_found", version = mc_version, path = instance_dir.display())))?;
+        let mut mc = info.install(self, name, progress).await?;
+        for component in &pack.components {
+            if MULTIMC_BUILTIN_COMPONENTS.contains(&component.uid.as_str()) {
+                continue;
+            }
+            let installer = MULTIMC_COMPONENTS.iter().find(|(uid, _)|*uid == component.uid).map(|v|v.1);
+            match (installer, &component.version) {
+                #[cfg(feature="components_installation")]
+                (Some(installer), Some(version)) => mc.install_component(installer, version, progress).await?,
+                _ => self.ui.warn(
+                    &t!("import.unsupported_component", component = component.uid),
+                    &t!("import.unsupported_component_title")
+                ).await
+            }
+        }
+
+        let game_dir = [".minecraft", "minecraft"].into_iter()
+            .map(|v|*(&dir / v))
+            .find(|v|v.0.is_dir());
+        check_cancelled(progress)?;
+        if let Some(game_dir) = game_dir {
+            copy_dir(&game_dir, &mc.version_root, &SKIPPED_DIRS).await?;
+        }
+        mc.extra_data.independent_game_dir = true;
+        ImportedSettings::from_multimc(cfg).apply(&mut mc);
+
+        let group = fs::read(instance_dir.join("../instgroups.json")).await.ok()
+            .and_then(|v|serde_json::from_slice::<Value>(&v).ok())
+            .and_then(|groups|{
+                let id = instance_dir.file_name()?.to_string_lossy().to_string();
+                groups["groups"].as_object()?.iter()
+                    .find(|(_, v)|v["instances"].as_array().is_some_and(|v|v.iter().any(|v|v == id.as_str())))
+                    .map(|(k, _)|k.clone())
+            });
+        mc.extra_data.metadata = InstanceMetadata {
+            display_name: non_empty(cfg.get("name")),
+            icon: non_empty(cfg.get("iconKey")).filter(|v|v != "default"),
+            group,
+            notes: non_empty(cfg.get("notes")),
+            last_played: cfg.get("lastLaunchTime").and_then(|v|v.parse().ok()).filter(|v|*v != 0),
+            total_play_time: cfg.get("totalTimePlayed").and_then(|v|v.parse().ok()).unwrap_or(0)
+        };
+        mc.save().await?;
+        Ok(self.get_installation(name).await.unwrap_or(mc))
+    }
+}
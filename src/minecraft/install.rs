@@ -1,24 +1,35 @@
 //! Things about installing Minecraft.
 
-use std::marker::PhantomData;
+use std::collections::HashMap;
 
-use anyhow::{Ok, Result};
-use sha1::Sha1;
-use tokio::{fs, io::AsyncReadExt, sync::mpsc};
+use anyhow::{anyhow, Ok, Result};
+use reqwest::{header::{ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED}, Method, StatusCode};
+use serde::{Deserialize, Serialize};
+use sha1::{Digest, Sha1};
+use tokio::{fs, io::AsyncReadExt};
 
-use crate::{utils::{check_hash, check_rules, download_all, download_txt, get_os, BetterPath, DownloadAllMessage}, LauncherContext};
+use crate::{java::tuning::MemorySettings, utils::{cancellable, check_res, check_rules, download_all, DownloadPhase, BetterPath, DownloadControl, HashAlgorithm, ProgressSink}, LauncherContext};
 
-use super::{schemas::{AssetsIndex, Library, Resource, VersionJSON}, version::{DMCLCExtraData, MinecraftInstallation}};
+use super::{instance::InstanceMetadata, schemas::{AssetsIndex, Library, LoggingInfo, Resource, VersionJSON}, version::{DMCLCExtraData, MinecraftInstallation}};
 /// The version list of Minecraft.
-pub use super::schemas::{VersionList, VersionInfo};
+pub use super::schemas::{VersionList, VersionInfo, VersionType};
 
-const MC_MANIFEST_URL: &str = "https://launchermeta.mojang.com/mc/game/version_manifest.json";
+const MC_MANIFEST_URL: &str = "https://piston-meta.mojang.com/mc/game/version_manifest_v2.json";
+
+#[derive(Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+struct ManifestCacheInfo {
+    etag: Option<String>,
+    last_modified: Option<String>
+}
 
 impl VersionList {
-    /// Gets the [VersionList].
-    pub async fn get_list() -> Result<VersionList> {
-        Ok(reqwest::get(MC_MANIFEST_URL)
+    /// Gets the [VersionList] without caching.
+    /// Consider using [LauncherContext::get_version_list] instead.
+    pub async fn get_list(launcher: &LauncherContext) -> Result<VersionList> {
+        Ok(launcher.get(MC_MANIFEST_URL)
             .await?
+            .error_for_status()?
             .json()
             .await?)
     }
@@ -35,78 +46,249 @@ impl VersionList {
 
     /// Get the latest snapshot in the [VersionList].
     pub fn get_latest_snapshot(&self) -> Option<&VersionInfo> {
-        self.find_by_id(&self.latest.release)
+        self.find_by_id(&self.latest.snapshot)
+    }
+
+    /// Get the versions whose type is in `types`, newest first.
+    pub fn filter<'a>(&'a self, types: &'a [VersionType]) -> impl Iterator<Item = &'a VersionInfo> {
+        self.versions.iter().filter(|i|types.contains(&i.typ))
+    }
+}
+
+impl LauncherContext {
+    /// Gets the [VersionList], which is cached in `<root>/cache`.
+    /// The cache is revalidated with `ETag` and `Last-Modified`, and it's used directly if the network is down.
+    pub async fn get_version_list(&self) -> Result<VersionList> {
+        let cache_dir = *(&self.root_path / "cache");
+        let cache_path = *(&cache_dir / "version_manifest_v2.json");
+        let info_path = *(&cache_dir / "version_manifest_v2.cache.json");
+        let cached = fs::read(&cache_path).await.ok()
+            .and_then(|v|serde_json::from_slice::<VersionList>(&v).ok());
+        let info: ManifestCacheInfo = match &cached {
+            Some(_) => fs::read(&info_path).await.ok()
+                .and_then(|v|serde_json::from_slice(&v).ok())
+                .unwrap_or_default(),
+            None => ManifestCacheInfo::default()
+        };
+        let res = self.send(Method::GET, MC_MANIFEST_URL, |mut req| {
+            if let Some(etag) = &info.etag {
+                req = req.header(IF_NONE_MATCH, etag);
+            }
+            if let Some(last_modified) = &info.last_modified {
+                req = req.header(IF_MODIFIED_SINCE, last_modified);
+            }
+            req
+        }).await.and_then(|v|v.error_for_status());
+        let res = match res {
+            Result::Ok(res) => res,
+            Err(e) => return cached.ok_or(e.into())
+        };
+        if res.status() == StatusCode::NOT_MODIFIED && let Some(cached) = cached {
+            return Ok(cached);
+        }
+        let header = |name|res.headers().get(name).and_then(|v|v.to_str().ok()).map(str::to_string);
+        let info = ManifestCacheInfo {
+            etag: header(ETAG),
+            last_modified: header(LAST_MODIFIED)
+        };
+        let text = match res.text().await {
+            Result::Ok(text) => text,
+            Err(e) => return cached.ok_or(e.into())
+        };
+        let list = serde_json::from_str(&text)?;
+        fs::create_dir_all(&cache_dir).await?;
+        fs::write(&cache_path, text).await?;
+        fs::write(&info_path, serde_json::to_vec(&info)?).await?;
+        Ok(list)
     }
 }
 
 impl VersionInfo {
+    /// Download the version JSON, which is checked with [Self::sha1] if it's known.
+    pub(crate) async fn get_json(&self, launcher: &LauncherContext) -> Result<String> {
+        let text = launcher.get(&self.url).await?.error_for_status()?.text().await?;
+        if let Some(sha1) = &self.sha1 && format!("{:x}", Sha1::digest(text.as_bytes())) != *sha1 {
+            return Err(anyhow!(t!("install.broken_version_json", version = self.id)));
+        }
+        Ok(text)
+    }
+
     /// Install
-    pub async fn install<'l>(&self, launcher: &'l LauncherContext, name: &str, channel: mpsc::UnboundedSender<DownloadAllMessage>) -> Result<MinecraftInstallation<'l>> {
-        let res = reqwest::get(&self.url).await?;
-        let text = res.text().await?;
-        let obj: VersionJSON = serde_json::from_str(&text)?;
+    /// If it's cancelled, the new version dir is removed.
+    pub async fn install<'l>(&self, launcher: &'l LauncherContext, name: &str, progress: &dyn ProgressSink) -> Result<MinecraftInstallation<'l>> {
         let version_dir = *(&launcher.root_path / "versions" / name);
-        fs::create_dir_all(version_dir.clone()).await?;
-        fs::write(&version_dir / format!("{name}.json"), text).await?;
+        let existed = fs::try_exists(&version_dir).await?;
+        let result = self.install_into(launcher, name, &version_dir, progress).await;
+        if result.is_err() && !existed && progress.control().is_some_and(DownloadControl::is_cancelled) {
+            let _ = fs::remove_dir_all(&version_dir).await;
+        }
+        result
+    }
+
+    async fn install_into<'l>(&self, launcher: &'l LauncherContext, name: &str, version_dir: &BetterPath, progress: &dyn ProgressSink) -> Result<MinecraftInstallation<'l>> {
+        let text = cancellable(progress, self.get_json(launcher)).await?;
+        let obj: VersionJSON = serde_json::from_str(&text)?;
+        fs::create_dir_all(version_dir).await?;
+        fs::write(version_dir / format!("{name}.json"), text).await?;
         let v = MinecraftInstallation::<'l>::new(launcher, obj, name, Some(DMCLCExtraData {
             version: Some(self.id.clone()),
             components: vec![],
+            generated_libraries: Some(vec![]),
             independent_game_dir: true,
             before_command: None,
+            wrapper_command: None,
+            after_command: None,
+            environment: HashMap::new(),
             with_java: None,
             extra_game_arguments: None,
-            extra_jvm_arguments: None
+            extra_jvm_arguments: None,
+            memory: MemorySettings::default(),
+            gc_preset: None,
+            metadata: InstanceMetadata::default()
         }));
-        v.complete_files(true, true, channel).await?;
+        v.save().await?;
+        v.complete_files(true, true, progress).await?;
         Ok(v)
     }
 }
 
 impl <'l> MinecraftInstallation<'l> {
     /// Download all the broken/missing files for the [MinecraftInstallation].
-    pub async fn complete_files(&self, always_download_nohash: bool, fix_client_jar: bool, channel: mpsc::UnboundedSender<DownloadAllMessage>) -> Result<()> {
+    pub async fn complete_files(&self, always_download_nohash: bool, fix_client_jar: bool, progress: &dyn ProgressSink) -> Result<()> {
+        let index = self.install_assets_index(progress).await?;
+        let resources = self.get_files(always_download_nohash, fix_client_jar, Some(&index))?;
+        download_all(
+            &resources, DownloadPhase::GameFiles, progress,
+            self.launcher.download_threads_per_file, self.launcher.download_parallel_files,
+            self.launcher.download_retries,self.launcher.mirror_provider.as_ref()
+        ).await?.into_result()?;
+        self.install_legacy_assets(&index).await?;
+        Ok(())
+    }
+
+    /// Get all files of the installation. Assets are included only if the `index` is given.
+    pub(crate) fn get_files(&self, always_download_nohash: bool, fix_client_jar: bool, index: Option<&AssetsIndex>) -> Result<Vec<(Resource, BetterPath)>> {
         let mut resources: Vec<(Resource, BetterPath)> = Vec::new();
         let client_res = &self.obj.get_base().downloads.client;
         let version_dir = *(&self.launcher.root_path / "versions" / &self.name);
         if fix_client_jar { resources.push((client_res.clone(), *(&version_dir / format!("{}.jar", self.name)))); }
-        resources.extend(self.install_resources().await?);
+        if let Some(index) = index {
+            resources.extend(self.get_asset_resources(index));
+        }
         resources.extend(self.install_libraries(&self.obj.get_base().libraries, always_download_nohash)?);
+        if let Some(logging) = &self.obj.get_base().logging.client {
+            resources.push((logging.file.res.clone(), self.get_logging_config_path(logging)));
+        }
+        Ok(resources)
+    }
+
+    pub(crate) fn get_logging_config_path(&self, logging: &LoggingInfo) -> BetterPath {
+        *(&self.launcher.root_path / "assets/log_configs" / &logging.file.id)
+    }
+
+    pub(crate) fn get_assets_index_path(&self) -> BetterPath {
+        *(&self.launcher.root_path / "assets/indexes" / &format!("{}.json", self.obj.get_base().asset_index.res.id))
+    }
+
+    /// Read the assets index if it's downloaded and not broken.
+    pub(crate) async fn read_assets_index(&self) -> Result<Option<AssetsIndex>> {
+        let assets = &self.obj.get_base().asset_index;
+        let asset_path = &self.get_assets_index_path();
+        if !check_res(&assets.res.res, asset_path).await {
+            return Ok(None);
+        }
+        let mut str = String::new();
+        tokio::fs::File::open(asset_path).await?.read_to_string(&mut str).await?;
+        Ok(Some(serde_json::from_str(&str)?))
+    }
+
+    async fn install_assets_index(&self, progress: &dyn ProgressSink) -> Result<AssetsIndex> {
+        if let Some(index) = self.read_assets_index().await? {
+            return Ok(index);
+        }
+        let path = self.get_assets_index_path();
         download_all(
-            &resources, channel,
+            &vec![(self.obj.get_base().asset_index.res.res.clone(), path.clone())], DownloadPhase::AssetsIndex, progress,
             self.launcher.download_threads_per_file, self.launcher.download_parallel_files,
-            self.launcher.download_retries,self.launcher.bmclapi_mirror.clone()
-        ).await?;
-        Ok(())
+            self.launcher.download_retries, self.launcher.mirror_provider.as_ref()
+        ).await?.into_result()?;
+        Ok(serde_json::from_str(&tokio::fs::read_to_string(&path).await?)?)
+    }
+
+    fn get_asset_path(hash: &str) -> String {
+        let first_two = hash.get(0..=1).unwrap(); // Must be ASCII.
+        format!("{first_two}/{hash}")
     }
 
-    async fn install_resources(&self) -> Result<Vec<(Resource, BetterPath)>> {
+    fn get_asset_resources(&self, index: &AssetsIndex) -> Vec<(Resource, BetterPath)> {
         let mut res = vec![];
-        let assets = &self.obj.get_base().asset_index;
-        let asset_path = &(&self.launcher.root_path / "assets/indexes" / &format!("{}.json", assets.res.id));
-        let index = if !check_hash(asset_path, &assets.res.res.sha1, assets.res.res.size, PhantomData::<Sha1>).await {
-            download_txt(&assets.res.res.url, asset_path).await?
-        } else {
-            let mut str = String::new();
-            tokio::fs::File::open(asset_path).await?.read_to_string(&mut str).await?;
-            str
-        };
-        let index: AssetsIndex = serde_json::from_str(&index)?;
         for (_, val) in index.objects.iter() {
-            let first_two = val.hash.get(0..=1).unwrap(); // Must be ASCII.
-            let path = format!("{first_two}/{}", val.hash);
+            let path = Self::get_asset_path(&val.hash);
             res.push((Resource {
                 url: format!("https://resources.download.minecraft.net/{path}"),
                 sha1: val.hash.clone(),
-                size: val.size
+                size: val.size,
+                ..Default::default()
             }, *(&self.launcher.root_path / "assets/objects" / &path)))
         }
-        Ok(res)
+        res
+    }
+
+    /// Get the directories that old versions read assets from by name, instead of `assets/objects`.
+    /// `virtual` indexes (like `legacy`) use `assets/virtual/<index>`, and `map_to_resources` ones (like `pre-1.6`) use `<gamedir>/resources`.
+    pub(crate) fn get_legacy_assets_dirs(&self, index: &AssetsIndex) -> Vec<BetterPath> {
+        let mut ret = vec![];
+        if index.is_virtual {
+            ret.push(*(&self.launcher.root_path / "assets/virtual" / &self.obj.get_base().asset_index.res.id));
+        }
+        if index.map_to_resources {
+            ret.push(*(&self.version_launch_work_dir / "resources"));
+        }
+        ret
+    }
+
+    /// Get the directory for `${game_assets}`.
+    /// If the index isn't downloaded, it's guessed by the index id.
+    pub(crate) fn get_game_assets_dir(&self, index: Option<&AssetsIndex>) -> BetterPath {
+        let id = &self.obj.get_base().asset_index.res.id;
+        let dirs = match index {
+            Some(index) => self.get_legacy_assets_dirs(index),
+            None => self.get_legacy_assets_dirs(&AssetsIndex {
+                objects: HashMap::new(),
+                is_virtual: id == "legacy",
+                map_to_resources: id == "pre-1.6"
+            })
+        };
+        dirs.into_iter().next().unwrap_or_else(||*(&self.launcher.root_path / "assets"))
+    }
+
+    /// Copy assets from `assets/objects` to the directories from [Self::get_legacy_assets_dirs].
+    async fn install_legacy_assets(&self, index: &AssetsIndex) -> Result<()> {
+        for dir in self.get_legacy_assets_dirs(index) {
+            for (name, val) in &index.objects {
+                let target = &dir / name;
+                if HashAlgorithm::Sha1.check(&target, &val.hash, val.size).await {
+                    continue;
+                }
+                if let Some(parent) = target.0.parent() {
+                    fs::create_dir_all(parent).await?;
+                }
+                fs::copy(&self.launcher.root_path / "assets/objects" / Self::get_asset_path(&val.hash), &*target).await?;
+            }
+        }
+        Ok(())
     }
     
-    pub(crate) fn install_libraries(&self, libraries: &Vec<Library>, always_download_nohash: bool) -> Result<Vec<(Resource, BetterPath)>> {
+    pub(crate) fn install_libraries(&self, libraries: &[Library], always_download_nohash: bool) -> Result<Vec<(Resource, BetterPath)>> {
+        self.launcher.get_library_resources(libraries, always_download_nohash)
+    }
+}
+
+impl LauncherContext {
+    pub(crate) fn get_library_resources(&self, libraries: &[Library], always_download_nohash: bool) -> Result<Vec<(Resource, BetterPath)>> {
         let mut res = vec![];
-        let lib_path = &*(&self.launcher.root_path / "libraries");
-        for lib in libraries {
+        let lib_path = &*(&self.root_path / "libraries");
+        for lib in &self.apply_library_overrides(libraries) {
             if !check_rules(&lib.get_base().rules) {
                 continue;
             }
@@ -115,7 +297,8 @@ impl <'l> MinecraftInstallation<'l> {
                     res.push((Resource {
                         url: format!("{}/{}", l.url, l.base.name.to_path()),
                         sha1: l.sha1.clone(),
-                        size: l.size
+                        size: l.size,
+                        ..Default::default()
                     }, *(lib_path / l.base.name.to_path())));
                 },
                 Library::FabricOldForgeAndLiteLoader(l) => {
@@ -125,15 +308,15 @@ impl <'l> MinecraftInstallation<'l> {
                     res.push((Resource {
                         url: format!("{}/{}", l.url, l.base.name.to_path()),
                         sha1: always_download_nohash.to_string(),
-                        size: 0
+                        size: 0,
+                        ..Default::default()
                     }, *(lib_path / l.base.name.to_path())));
                 }
                 Library::VanillaForgeAndNeo(l) => {
                     res.push((l.downloads.artifact.res.clone(), *(lib_path / &l.downloads.artifact.path)))
                 }
                 Library::VanillaNatives(l) => {
-                    if let Some(os) = l.natives.get(&get_os()) {
-                        let artifact = l.downloads.classifiers.get(os).unwrap();
+                    if let Some(artifact) = l.get_native_artifact() {
                         res.push((artifact.res.clone(), *(lib_path / &artifact.path)))
                     }
                 }
@@ -141,7 +324,8 @@ impl <'l> MinecraftInstallation<'l> {
                     res.push((Resource {
                         url: format!("https://libraries.minecraft.net/{}", l.name.to_path()),
                         sha1: always_download_nohash.to_string(),
-                        size: 0
+                        size: 0,
+                        ..Default::default()
                     }, *(lib_path / l.name.to_path())));
                 }
             }
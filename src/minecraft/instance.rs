@@ -0,0 +1,276 @@
+//! Things about managing Minecraft installations.
+
+use std::{path::Path, time::{Duration, SystemTime, UNIX_EPOCH}};
+
+use anyhow::{anyhow, Ok, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::fs;
+
+use crate::{utils::BetterPath, LauncherContext};
+
+use super::version::{DMCLCExtraData, MinecraftInstallation, EXTRA_DATA_FILE};
+
+/// User-facing informations of an installation, stored in [DMCLCExtraData::metadata].
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct InstanceMetadata {
+    /// The name shown to users. The installation name is used if it's [None].
+    pub display_name: Option<String>,
+    /// The icon, like a path or a built-in icon name. It's up to the client.
+    pub icon: Option<String>,
+    /// The group for sorting installations.
+    pub group: Option<String>,
+    /// Notes from the user.
+    pub notes: Option<String>,
+    /// When the game was played last time, in milliseconds since the Unix epoch.
+    pub last_played: Option<u64>,
+    /// Total play time in seconds.
+    #[serde(default)]
+    pub total_play_time: u64
+}
+
+/// What [LauncherContext::duplicate_installation] copies besides the version files and settings.
+/// They are shared if the independent game dir isn't enabled.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DuplicateOptions {
+    /// Copy `saves`.
+    pub saves: bool,
+    /// Copy `mods`.
+    pub mods: bool
+}
+
+#[derive(Deserialize)]
+struct MetadataOnly {
+    #[serde(default)]
+    metadata: InstanceMetadata
+}
+
+/// Directories that are generated again, so they aren't copied.
+pub(crate) const SKIPPED_DIRS: [&str; 3] = ["natives", "logs", "crash-reports"];
+
+pub(crate) fn check_name(name: &str) -> Result<()> {
+    if name.is_empty() || name == "." || name == ".." || name.trim() != name
+        || name.chars().any(|c|c.is_control() || r#"/\:*?"<>|"#.contains(c)) {
+        return Err(anyhow!(t!("instance.invalid_name", name = name)));
+    }
+    Ok(())
+}
+
+pub(crate) fn now_millis() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |v|v.as_millis() as u64)
+}
+
+/// Remove `dir` if `result` is an error, so a failed operation doesn't leave a broken installation.
+pub(crate) async fn remove_on_error<T>(dir: &BetterPath, result: Result<T>) -> Result<T> {
+    if result.is_err() {
+        let _ = fs::remove_dir_all(dir).await;
+    }
+    result
+}
+
+/// Rename `<old>.json` and `<old>.jar` in `dir`, and change `id` in the version JSON.
+/// If it fails, the files are kept as they were.
+pub(crate) async fn rename_version_files(dir: &BetterPath, old: &str, new: &str) -> Result<()> {
+    let mut json: Value = serde_json::from_slice(&fs::read(dir / format!("{old}.json")).await?)?;
+    json["id"] = Value::String(new.to_string());
+    let new_json = *(dir / format!("{new}.json"));
+    fs::write(&new_json, serde_json::to_vec_pretty(&json)?).await?;
+    let jar = *(dir / format!("{old}.jar"));
+    if jar.0.is_file() && let Err(e) = fs::rename(&jar, dir / format!("{new}.jar")).await {
+        if old != new {
+            let _ = fs::remove_file(&new_json).await;
+        }
+        return Err(e.into());
+    }
+    if old != new {
+        // The installation is already complete with the new name.
+        let _ = fs::remove_file(dir / format!("{old}.json")).await;
+    }
+    Ok(())
+}
+
+/// Copy a symlink as a symlink, instead of what it points to.
+async fn copy_symlink(from: &Path, to: &Path) -> Result<()> {
+    let target = fs::read_link(from).await?;
+    #[cfg(unix)]
+    fs::symlink(&target, to).await?;
+    #[cfg(windows)]
+    if fs::metadata(from).await.is_ok_and(|v|v.is_dir()) {
+        fs::symlink_dir(&target, to).await?;
+    } else {
+        fs::symlink_file(&target, to).await?;
+    }
+    Ok(())
+}
+
+pub(crate) async fn copy_dir(from: &BetterPath, to: &BetterPath, skip: &[&str]) -> Result<()> {
+    let mut stack = vec![(from.clone(), to.clone(), true)];
+    while let Some((from, to, top)) = stack.pop() {
+        fs::create_dir_all(&to).await?;
+        let mut entries = fs::read_dir(&from).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            let name = entry.file_name();
+            if top && skip.iter().any(|v|name == *v) {
+                continue;
+            }
+            let file_type = entry.file_type().await?;
+            if file_type.is_symlink() {
+                copy_symlink(&entry.path(), &(&to / &name).0).await?;
+            } else if file_type.is_dir() {
+                stack.push((*(&from / &name), *(&to / &name), false));
+            } else {
+                fs::copy(entry.path(), &to / &name).await?;
+            }
+        }
+    }
+    Ok(())
+}
+
+impl LauncherContext {
+    fn get_version_dir(&self, name: &str) -> Result<BetterPath> {
+        let dir = *(&self.root_path / "versions" / name);
+        if !(&dir / format!("{name}.json")).0.is_file() {
+            return Err(anyhow!(t!("instance.not_found", name = name)));
+        }
+        Ok(dir)
+    }
+
+    pub(crate) async fn check_new_name(&self, name: &str) -> Result<BetterPath> {
+        check_name(name)?;
+        let dir = *(&self.root_path / "versions" / name);
+        if fs::try_exists(&dir).await? {
+            return Err(anyhow!(t!("instance.already_exists", name = name)));
+        }
+        Ok(dir)
+    }
+
+    async fn read_version_json(&self, name: &str) -> Option<Value> {
+        serde_json::from_slice(&fs::read(&self.root_path / "versions" / name / format!("{name}.json")).await.ok()?).ok()
+    }
+
+    /// Find the installations that inherit from `name` with `inheritsFrom`.
+    pub async fn find_inheritors(&self, name: &str) -> Result<Vec<String>> {
+        let mut ret = vec![];
+        for i in self.list_installations().await? {
+            if let Some(json) = self.read_version_json(&i).await && json["inheritsFrom"] == name {
+                ret.push(i);
+            }
+        }
+        Ok(ret)
+    }
+
+    async fn set_inherits_from(&self, name: &str, parent: &str) -> Result<()> {
+        let path = *(&self.root_path / "versions" / name / format!("{name}.json"));
+        let mut json: Value = serde_json::from_slice(&fs::read(&path).await?)?;
+        json["inheritsFrom"] = Value::String(parent.to_string());
+        fs::write(&path, serde_json::to_vec_pretty(&json)?).await?;
+        Ok(())
+    }
+
+    /// Rename an installation.
+    /// The version JSON and the client jar are renamed, and `inheritsFrom` of other installations is changed.
+    /// If it fails, everything is changed back.
+    pub async fn rename_installation(&self, name: &str, new_name: &str) -> Result<()> {
+        let old_dir = self.get_version_dir(name)?;
+        let new_dir = self.check_new_name(new_name).await?;
+        let inheritors = self.find_inheritors(name).await?;
+        fs::rename(&old_dir, &new_dir).await?;
+        if let Err(e) = rename_version_files(&new_dir, name, new_name).await {
+            let _ = fs::rename(&new_dir, &old_dir).await;
+            return Err(e);
+        }
+        let mut changed: Vec<String> = vec![];
+        for i in inheritors {
+            if let Err(e) = self.set_inherits_from(&i, new_name).await {
+                for i in &changed {
+                    let _ = self.set_inherits_from(i, name).await;
+                }
+                let _ = rename_version_files(&new_dir, new_name, name).await;
+                let _ = fs::rename(&new_dir, &old_dir).await;
+                return Err(e);
+            }
+            changed.push(i);
+        }
+        Ok(())
+    }
+
+    /// Copy an installation to `new_name`.
+    /// Play time in [InstanceMetadata] isn't copied. Symlinks are copied as symlinks.
+    /// It's copied to a temporary dir first, so nothing is left if it fails.
+    pub async fn duplicate_installation(&self, name: &str, new_name: &str, options: DuplicateOptions) -> Result<()> {
+        let old_dir = self.get_version_dir(name)?;
+        let new_dir = self.check_new_name(new_name).await?;
+        // It isn't listed as an installation, because the version JSON doesn't match the dir name.
+        let staging = *(&self.root_path / "versions" / format!(".{new_name}-{}", now_millis()));
+        let result = async {
+            let mut skip = SKIPPED_DIRS.to_vec();
+            if !options.saves {
+                skip.push("saves");
+            }
+            if !options.mods {
+                skip.push("mods");
+            }
+            copy_dir(&old_dir, &staging, &skip).await?;
+            rename_version_files(&staging, name, new_name).await?;
+            let extras_path = *(&staging / EXTRA_DATA_FILE);
+            if let Some(mut extras) = fs::read(&extras_path).await.ok()
+                .and_then(|v|serde_json::from_slice::<DMCLCExtraData>(&v).ok()) {
+                extras.metadata.last_played = None;
+                extras.metadata.total_play_time = 0;
+                fs::write(&extras_path, serde_json::to_vec(&extras)?).await?;
+            }
+            fs::rename(&staging, &new_dir).await?;
+            Ok(())
+        }.await;
+        remove_on_error(&staging, result).await
+    }
+
+    /// Move an installation to `<root>/trash`, and return where it's moved to.
+    /// It fails if other installations inherit from it, see [Self::find_inheritors].
+    pub async fn delete_installation(&self, name: &str) -> Result<BetterPath> {
+        let dir = self.get_version_dir(name)?;
+        let inheritors = self.find_inheritors(name).await?;
+        if !inheritors.is_empty() {
+            return Err(anyhow!(t!("instance.inherited", name = name, inheritors = inheritors.join(", "))));
+        }
+        let trash = *(&self.root_path / "trash");
+        fs::create_dir_all(&trash).await?;
+        let target = *(&trash / format!("{name}-{}", now_millis()));
+        fs::rename(&dir, &target).await?;
+        Ok(target)
+    }
+
+    /// Delete everything in `<root>/trash` permanently.
+    pub async fn empty_trash(&self) -> Result<()> {
+        let trash = *(&self.root_path / "trash");
+        if fs::try_exists(&trash).await? {
+            fs::remove_dir_all(&trash).await?;
+        }
+        Ok(())
+    }
+
+    /// Read [InstanceMetadata] of all installations, without loading them.
+    pub async fn list_installation_metadata(&self) -> Result<Vec<(String, InstanceMetadata)>> {
+        let mut ret = vec![];
+        for name in self.list_installations().await? {
+            let metadata = fs::read(&self.root_path / "versions" / &name / EXTRA_DATA_FILE).await.ok()
+                .and_then(|v|serde_json::from_slice::<MetadataOnly>(&v).ok())
+                .map(|v|v.metadata)
+                .unwrap_or_default();
+            ret.push((name, metadata));
+        }
+        Ok(ret)
+    }
+}
+
+impl MinecraftInstallation<'_> {
+    /// Record a play session in [InstanceMetadata], and save [Self::extra_data].
+    /// Call it with [Duration::ZERO] when the game starts, and with the played time when it exits.
+    pub async fn record_play(&mut self, played: Duration) -> Result<()> {
+        let metadata = &mut self.extra_data.metadata;
+        metadata.last_played = Some(now_millis());
+        metadata.total_play_time += played.as_secs();
+        self.save().await
+    }
+}
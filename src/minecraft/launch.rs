@@ -1,83 +1,401 @@
 //! Things about launching Minecraft.
 
-use std::{collections::HashMap, ffi::{OsStr, OsString}, fs::File, path::PathBuf};
+use std::{collections::HashMap, ffi::{OsStr, OsString}, fs::File, path::PathBuf, process::Stdio};
 
-use anyhow::{Ok, Result};
+use anyhow::{anyhow, Ok, Result};
 use osstrtools_fix::{Bytes, OsStringTools};
-use tokio::sync::mpsc;
-use uuid::Uuid;
+use tokio::process::Command;
 
-use crate::utils::{check_rules, check_rules_no_option, get_bits, get_os, DownloadAllMessage, PATH_DELIMITER};
+use crate::{java::{installation::{select_java, which_java, JavaInstallation}, runtime::get_runtime_platform, tuning::get_tuning_args}, utils::{check_rules, check_rules_no_option, split_command_line, BetterPath, ProgressSink, PATH_DELIMITER}, LauncherContext};
 
-use super::{login::Account, schemas::{Argument, Library, OneOrMoreArguments, VersionJSON}, version::MinecraftInstallation};
+use super::{login::Account, process::{GameProcess, LogMasker}, schemas::{Argument, AssetsIndex, JavaInfo, Library, OneOrMoreArguments, VersionJSON}, version::MinecraftInstallation};
+
+/// Where to go directly after the game is launched.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum QuickPlay {
+    /// A singleplayer world, by the name of its folder in `saves`.
+    Singleplayer(String),
+    /// A multiplayer server.
+    Multiplayer {
+        /// Host name or IP address.
+        host: String,
+        /// Port. The default port 25565 is used if it's [None].
+        port: Option<u16>
+    },
+    /// A Realm, by its ID.
+    Realms(String)
+}
+
+/// Options for launching.
+#[derive(Debug, Clone, Default)]
+pub struct LaunchOptions {
+    /// Features for argument rules, like `has_custom_resolution` and `is_demo_user`.
+    pub features: HashMap<String, bool>,
+    /// Values for placeholders in arguments, like `${resolution_width}`.
+    /// The keys contain `${` and `}`.
+    pub placeholders: HashMap<String, String>,
+    /// Where to go directly after the game is launched.
+    /// Versions without Quick Play support can only join a [QuickPlay::Multiplayer] server.
+    pub quick_play: Option<QuickPlay>
+}
+
+impl LaunchOptions {
+    /// Create an empty [LaunchOptions].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set a feature.
+    pub fn set_feature(&mut self, feature: &str, enabled: bool) -> &mut Self {
+        self.features.insert(feature.to_string(), enabled);
+        self
+    }
+
+    /// Set the value of a placeholder, without `${` and `}`.
+    pub fn set_placeholder(&mut self, name: &str, value: &str) -> &mut Self {
+        self.placeholders.insert(format!("${{{name}}}"), value.to_string());
+        self
+    }
+
+    /// Set the window size.
+    pub fn set_resolution(&mut self, width: u32, height: u32) -> &mut Self {
+        self.set_feature("has_custom_resolution", true)
+            .set_placeholder("resolution_width", &width.to_string())
+            .set_placeholder("resolution_height", &height.to_string())
+    }
+
+    /// Launch the game in demo mode.
+    pub fn set_demo(&mut self, demo: bool) -> &mut Self {
+        self.set_feature("is_demo_user", demo)
+    }
+
+    /// Let the game write Quick Play logs to the `path`, relative to the game directory.
+    pub fn set_quick_play_path(&mut self, path: &str) -> &mut Self {
+        self.set_feature("has_quick_plays_support", true)
+            .set_placeholder("quickPlayPath", path)
+    }
+
+    /// Go directly to a world, a server or a Realm after the game is launched.
+    pub fn set_quick_play(&mut self, quick_play: QuickPlay) -> &mut Self {
+        self.quick_play = Some(quick_play);
+        self
+    }
+
+    fn has_feature(&self, feature: &str) -> bool {
+        self.features.get(feature).copied().unwrap_or(false)
+    }
+}
+
+/// Split a command in [super::version::DMCLCExtraData].
+/// Returns an empty [Vec] if it's [None].
+pub(crate) fn parse_command(command: &Option<String>) -> Result<Vec<String>> {
+    let Some(command) = command else {
+        return Ok(vec![]);
+    };
+    split_command_line(command).ok_or(anyhow!(t!("launch.invalid_command", command = command)))
+}
+
+/// The Java chosen by [MinecraftInstallation::choose_java].
+#[derive(Debug, Clone)]
+pub enum JavaChoice {
+    /// [super::version::DMCLCExtraData::with_java].
+    Configured(OsString),
+    /// A Java runtime provided by Mojang.
+    Runtime {
+        /// The component name, like `java-runtime-delta`.
+        component: String,
+        /// The java executable. It's [None] if the runtime isn't installed.
+        java: Option<BetterPath>
+    },
+    /// A suitable Java on the system.
+    System(BetterPath),
+    /// No suitable Java is found, so `java` in `PATH` is used.
+    Path
+}
+
+impl JavaChoice {
+    /// Get the major version of the chosen Java.
+    /// The required one in `java_info` is returned if it can't be probed, like a runtime that isn't installed yet.
+    /// See [JavaInstallation::probe_with] for `run_java`.
+    pub(crate) async fn major_version(&self, java_info: &JavaInfo, run_java: bool) -> usize {
+        let java = match self {
+            JavaChoice::Configured(java) => which_java(java),
+            JavaChoice::Runtime { java: Some(java), .. } | JavaChoice::System(java) => java.0.clone(),
+            JavaChoice::Runtime { java: None, .. } => return java_info.major_version,
+            JavaChoice::Path => which_java(&OsString::from("java"))
+        };
+        JavaInstallation::probe_with(&java, run_java).await.map_or(java_info.major_version, |v|v.major_version)
+    }
+}
+
+impl LauncherContext {
+    /// Choose the Java for `java_info`. See [JavaInstallation::probe_with] for `run_java`.
+    pub(crate) async fn choose_java(&self, with_java: Option<&str>, java_info: &JavaInfo, run_java: bool) -> JavaChoice {
+        if let Some(java) = with_java {
+            return JavaChoice::Configured(OsString::from(java));
+        }
+        if !java_info.component.is_empty() && get_runtime_platform().is_some() {
+            return JavaChoice::Runtime {
+                component: java_info.component.clone(),
+                java: self.get_java_runtime(&java_info.component)
+            };
+        }
+        if let Some(java) = select_java(&self.find_java_installations_with(run_java).await, java_info) {
+            return JavaChoice::System(java.path.clone());
+        }
+        JavaChoice::Path
+    }
+}
 
 impl <'a> MinecraftInstallation<'a> {
+    /// Launch the game.
+    /// It runs [super::version::DMCLCExtraData::before_command], then starts the java from [Self::get_java] in [Self::get_cwd],
+    /// with [super::version::DMCLCExtraData::wrapper_command] and [super::version::DMCLCExtraData::environment].
+    /// The output is masked with [Account::get_log_masks].
+    pub async fn launch(&self, account: &mut dyn Account, options: &LaunchOptions, progress: &dyn ProgressSink) -> Result<GameProcess> {
+        let wrapper = parse_command(&self.extra_data.wrapper_command)?;
+        let after_exit = parse_command(&self.extra_data.after_command)?.split_first().map(|(program, args)|{
+            let mut command = Command::new(program);
+            command.args(args).current_dir(self.get_cwd()).envs(&self.extra_data.environment);
+            command
+        });
+        let java = self.get_java(progress).await?;
+        let args = self.launch_args(account, options, progress).await?;
+        self.run_before_command().await?;
+        let mut command = match wrapper.split_first() {
+            Some((program, wrapper_args)) => {
+                let mut command = Command::new(program);
+                command.args(wrapper_args).arg(java);
+                command
+            },
+            None => Command::new(java)
+        };
+        GameProcess::spawn(
+            command.args(args).current_dir(self.get_cwd()).envs(&self.extra_data.environment).stdin(Stdio::null()),
+            LogMasker::new(account.get_log_masks()),
+            after_exit
+        )
+    }
+
+    /// Mask secrets of the `account` in the game's `logs/latest.log` and write it to `dest`.
+    /// Use it before uploading the log.
+    pub async fn export_latest_log(&self, account: &dyn Account, dest: &BetterPath) -> Result<()> {
+        LogMasker::new(account.get_log_masks()).mask_file(&(&self.version_launch_work_dir / "logs/latest.log"), dest).await
+    }
+
+    /// Get the java command that should be used.
+    /// See [Self::choose_java] for how it's chosen. The Java runtime is installed if missing.
+    /// The user is warned if the Java doesn't match [super::schemas::JavaInfo::major_version].
+    pub async fn get_java(&self, progress: &dyn ProgressSink) -> Result<OsString> {
+        match self.choose_java().await {
+            JavaChoice::Configured(java) => {
+                self.warn_java_mismatch(&java).await;
+                Ok(java)
+            },
+            JavaChoice::Runtime { java: Some(java), .. } => Ok(java.0.into_os_string()),
+            JavaChoice::Runtime { component, java: None } => Ok(self.launcher.install_java_runtime(&component, progress).await?.0.into_os_string()),
+            JavaChoice::System(java) => Ok(java.0.into_os_string()),
+            JavaChoice::Path => {
+                let java = OsString::from("java");
+                self.warn_java_mismatch(&java).await;
+                Ok(java)
+            }
+        }
+    }
+
+    /// Choose the Java without installing anything.
+    /// If [super::version::DMCLCExtraData::with_java] isn't set, the Java runtime provided by Mojang is used.
+    /// If Mojang doesn't provide one for this version or the current platform, a suitable Java on the system is selected.
+    pub async fn choose_java(&self) -> JavaChoice {
+        self.choose_java_with(true).await
+    }
+
+    /// Like [Self::choose_java]. See [JavaInstallation::probe_with] for `run_java`.
+    pub(crate) async fn choose_java_with(&self, run_java: bool) -> JavaChoice {
+        self.launcher.choose_java(self.extra_data.with_java.as_deref(), &self.obj.get_base().java_version, run_java).await
+    }
+
+    async fn warn_java_mismatch(&self, java: &OsString) {
+        let java_info = &self.obj.get_base().java_version;
+        if let Result::Ok(installation) = JavaInstallation::probe(&which_java(java)).await && !installation.is_suitable_for(java_info) {
+            self.launcher.ui.warn(
+                &t!("java.mismatch", required = java_info.major_version, current = installation.major_version, path = installation.path.0.display()),
+                &t!("java.mismatch_title")
+            ).await;
+        }
+    }
+
+    async fn run_before_command(&self) -> Result<()> {
+        let command = parse_command(&self.extra_data.before_command)?;
+        let Some((program, args)) = command.split_first() else {
+            return Ok(());
+        };
+        let status = Command::new(program)
+            .args(args)
+            .current_dir(self.get_cwd())
+            .envs(&self.extra_data.environment)
+            .status().await?;
+        if !status.success() {
+            return Err(anyhow!(t!("launch.before_command_failed", status = status)));
+        }
+        Ok(())
+    }
+
     /// Generate the launch arguments.
     /// Please run [super::version::DMCLCExtraData::before_command] before launching.
-    /// Please use [super::version::DMCLCExtraData::with_java].
+    /// Please use the java from [Self::get_java].
     /// Please set the work dir to [Self::get_cwd].
-    pub async fn launch_args(&self, account: &mut dyn Account, download_channel: mpsc::UnboundedSender<DownloadAllMessage>) -> Result<Vec<OsString>> {
+    /// Consider using [Self::launch] instead.
+    pub async fn launch_args(&self, account: &mut dyn Account, options: &LaunchOptions, progress: &dyn ProgressSink) -> Result<Vec<OsString>> {
         if !account.is_initialized() || !account.check(&self.launcher).await {
             account.login(&self.launcher).await?;
         }
         account.prepare_launch(&self.version_launch_work_dir, &self.launcher).await?;
-        self.complete_files(false, false, download_channel).await?;
+        self.complete_files(false, false, progress).await?;
         self.unzip_natives()?;
-        let mut args = vec![];
+        let mut account_game_args = account.get_launch_game_args(&self.launcher).await;
+        account_game_args.insert("${auth_uuid}".to_string(), account.get_uuid().simple().to_string());
+        let account_jvm_args = match &self.obj {
+            VersionJSON::New { .. } => account.get_launch_jvmargs(self, &self.launcher).await?,
+            VersionJSON::Old { .. } => vec![]
+        };
+        let index = self.read_assets_index().await?;
+        let java_major_version = self.choose_java().await.major_version(&self.obj.get_base().java_version, true).await;
+        let (jvm_args, game_args) = self.gen_args(options, account_jvm_args, &account_game_args, index.as_ref(), java_major_version)?;
+        let mut args = jvm_args;
+        args.push(OsString::from(&self.obj.get_base().main_class));
+        args.extend(game_args);
+        Ok(args)
+    }
+
+    /// Generate the JVM arguments and the game arguments, without the main class.
+    /// Placeholders in `account_args` are replaced, and others from the account are kept.
+    /// `${game_assets}` is chosen by the assets `index`, see [Self::get_game_assets_dir].
+    /// The GC preset is chosen for the Java with `java_major_version`.
+    pub(crate) fn gen_args(&self, options: &LaunchOptions, account_jvm_args: Vec<OsString>, account_args: &HashMap<String, String>, index: Option<&AssetsIndex>, java_major_version: usize) -> Result<(Vec<OsString>, Vec<OsString>)> {
+        let (options, quick_play_args) = &self.resolve_quick_play(options)?;
+        let mut placeholders = account_args.clone();
+        placeholders.insert("${game_assets}".to_string(), self.get_game_assets_dir(index).0.to_string_lossy().to_string());
+        let account_args = &placeholders;
+        let mut jvm_args = vec![];
+        let mut game_args = vec![];
         let cp = self.gen_classpath().join(PATH_DELIMITER.bytes_as_os_str());
-        let account_game_args = account.get_launch_game_args(&self.launcher).await;
         match &self.obj {
-            VersionJSON::Old { base, minecraft_arguments } => {
+            VersionJSON::Old { base: _, minecraft_arguments } => {
                 let mut lib = OsString::from("-Djava.library.path=");
                 lib.push((&self.version_root / "natives").0.into_os_string());
-                args.push(lib);
-                args.push(OsString::from("-cp"));
-                args.push(cp.clone());
-                args.extend(self.extra_data.extra_jvm_arguments.clone().into_iter().flatten());
-                args.push(OsString::from(&base.main_class));
+                jvm_args.push(lib);
+                jvm_args.push(OsString::from("-cp"));
+                jvm_args.push(cp.clone());
+                jvm_args.extend(account_jvm_args);
+                jvm_args.extend(self.get_logging_args());
+                jvm_args.extend(self.get_tuning_args(java_major_version));
+                jvm_args.extend(self.extra_data.extra_jvm_arguments.clone().into_iter().flatten());
                 for i in minecraft_arguments.split(" ") {
-                    args.extend(self.transform_arg(&Argument::String(i.to_string()), &cp, &account_game_args, &account.get_uuid()));
+                    game_args.extend(self.transform_arg(&Argument::String(i.to_string()), &cp, account_args, options));
+                }
+                // Old versions don't have feature-gated arguments.
+                if options.has_feature("is_demo_user") {
+                    game_args.push(OsString::from("--demo"));
+                }
+                if options.has_feature("has_custom_resolution") {
+                    for i in ["--width", "${resolution_width}", "--height", "${resolution_height}"] {
+                        game_args.extend(self.transform_arg(&Argument::String(i.to_string()), &cp, account_args, options));
+                    }
                 }
-                args.extend(self.extra_data.extra_game_arguments.clone().into_iter().flatten());
             },
             VersionJSON::New { base: _, arguments } => {
                 if let Some(jvm) = &arguments.jvm {
                     for i in jvm {
-                        args.extend(self.transform_arg(i, &cp, &account_game_args, &account.get_uuid()));
+                        jvm_args.extend(self.transform_arg(i, &cp, account_args, options));
                     }
                 }
 
-                args.extend(account.get_launch_jvmargs(self, &self.launcher).await?);
-                args.extend(self.extra_data.extra_jvm_arguments.clone().into_iter().flatten());
-                args.push(OsString::from(self.obj.get_base().main_class.clone()));
+                jvm_args.extend(account_jvm_args);
+                jvm_args.extend(self.get_logging_args());
+                jvm_args.extend(self.get_tuning_args(java_major_version));
+                jvm_args.extend(self.extra_data.extra_jvm_arguments.clone().into_iter().flatten());
 
                 if let Some(game) = &arguments.game {
                     for i in game {
-                        args.extend(self.transform_arg(i, &cp, &account_game_args, &account.get_uuid()));
-                        args.extend(self.extra_data.extra_jvm_arguments.clone().into_iter().flatten());
+                        game_args.extend(self.transform_arg(i, &cp, account_args, options));
                     }
                 }
             }
         }
-        Ok(args)
+        game_args.extend(quick_play_args.iter().cloned());
+        game_args.extend(self.extra_data.extra_game_arguments.clone().into_iter().flatten());
+        Ok((jvm_args, game_args))
+    }
+
+    /// Check if this version supports Quick Play arguments like `--quickPlayMultiplayer`.
+    pub fn supports_quick_play(&self) -> bool {
+        let VersionJSON::New { arguments, .. } = &self.obj else {
+            return false;
+        };
+        arguments.game.iter().flatten().any(|v|match v {
+            Argument::String(v) => v == "--quickPlayMultiplayer",
+            Argument::Conditional { value: OneOrMoreArguments::One(v), .. } => v == "--quickPlayMultiplayer",
+            Argument::Conditional { value: OneOrMoreArguments::More(v), .. } => v.iter().any(|v|v == "--quickPlayMultiplayer")
+        })
+    }
+
+    /// Turn [LaunchOptions::quick_play] into features and placeholders, or legacy arguments for old versions.
+    fn resolve_quick_play(&self, options: &LaunchOptions) -> Result<(LaunchOptions, Vec<OsString>)> {
+        let mut options = options.clone();
+        let Some(quick_play) = options.quick_play.clone() else {
+            return Ok((options, vec![]));
+        };
+        if self.supports_quick_play() {
+            match quick_play {
+                QuickPlay::Singleplayer(world) => options.set_feature("is_quick_play_singleplayer", true).set_placeholder("quickPlaySingleplayer", &world),
+                QuickPlay::Multiplayer { host, port } => options.set_feature("is_quick_play_multiplayer", true)
+                    .set_placeholder("quickPlayMultiplayer", &format!("{host}:{}", port.unwrap_or(25565))),
+                QuickPlay::Realms(realm) => options.set_feature("is_quick_play_realms", true).set_placeholder("quickPlayRealms", &realm)
+            };
+            return Ok((options, vec![]));
+        }
+        match quick_play {
+            QuickPlay::Multiplayer { host, port } => Ok((options, vec![
+                OsString::from("--server"), OsString::from(host),
+                OsString::from("--port"), OsString::from(port.unwrap_or(25565).to_string())
+            ])),
+            _ => Err(anyhow!(t!("launch.quick_play_unsupported")))
+        }
+    }
+
+    /// Get the arguments from [super::version::DMCLCExtraData::memory] and [super::version::DMCLCExtraData::gc_preset].
+    /// The GC preset is chosen for the Java with `java_major_version`.
+    fn get_tuning_args(&self, java_major_version: usize) -> Vec<OsString> {
+        get_tuning_args(&self.extra_data.memory, self.extra_data.gc_preset, &(&self.version_launch_work_dir / "mods"), java_major_version)
+    }
+
+    fn get_logging_args(&self) -> Option<OsString> {
+        let logging = self.obj.get_base().logging.client.as_ref()?;
+        Some(OsString::from(&logging.argument).replace("${path}", self.get_logging_config_path(logging).0.as_os_str()))
     }
 
     fn unzip_natives(&self) -> Result<()> {
-        for i in &self.obj.get_base().libraries {
+        for libpath in self.get_native_archives() {
+            zip::ZipArchive::new(File::open(libpath)?)?.extract(&self.version_root / "natives")?;
+        }
+        Ok(())
+    }
+
+    pub(crate) fn get_native_archives(&self) -> Vec<BetterPath> {
+        let mut ret = vec![];
+        for i in &self.launcher.apply_library_overrides(&self.obj.get_base().libraries) {
             if let Library::VanillaNatives(n) = i {
                 if !check_rules(&i.get_base().rules) {
                     continue;
                 }
-                let env = &n.natives[&get_os()].replace("${arch}", &get_bits());
-                if !n.downloads.classifiers.contains_key(env) {
+                let Some(native) = n.get_native_artifact() else {
                     continue;
-                }
-                let native = &n.downloads.classifiers[env];
-                let libpath = &self.launcher.root_path / "libraries" / native.path.clone();
-                zip::ZipArchive::new(File::open(libpath)?)?.extract(&self.version_root / "natives")?;
+                };
+                ret.push(*(&self.launcher.root_path / "libraries" / native.path.clone()));
             }
         }
-        Ok(())
+        ret
     }
 
     /// Get the correct work dir.
@@ -85,12 +403,12 @@ impl <'a> MinecraftInstallation<'a> {
         self.version_launch_work_dir.0.clone()
     }
 
-    fn transform_arg(&self, arg: &Argument, cp: &OsStr, account_args: &HashMap<String, String>, account_uuid: &Uuid) -> Vec<OsString> {
+    fn transform_arg(&self, arg: &Argument, cp: &OsStr, account_args: &HashMap<String, String>, options: &LaunchOptions) -> Vec<OsString> {
         let mut args = vec![];
         match arg {
             Argument::String(s) => args.push(OsString::from(s)),
             Argument::Conditional{rules, value} => {
-                if !check_rules_no_option(rules) {
+                if !check_rules_no_option(rules, &options.features) {
                     return vec![];
                 }
                 match value {
@@ -105,7 +423,6 @@ impl <'a> MinecraftInstallation<'a> {
                 .replace("${game_directory}", self.version_launch_work_dir.0.as_os_str())
                 .replace("${assets_root}", (&self.launcher.root_path / "assets").0.as_os_str())
                 .replace("${assets_index_name}", self.obj.get_base().assets.as_str())
-                .replace("${auth_uuid}", account_uuid.simple().to_string().as_str())
                 .replace("${version_type}", "DMCL5")
                 .replace("${natives_directory}", (&self.version_root / "natives").0.as_os_str())
                 .replace("${launcher_name}", "DMCLC5")
@@ -113,7 +430,7 @@ impl <'a> MinecraftInstallation<'a> {
                 .replace("${library_directory}", (&self.launcher.root_path / "libraries").0.as_os_str())
                 .replace("${classpath_separator}", PATH_DELIMITER)
                 .replace("${classpath}", cp);
-            for (k, v) in account_args {
+            for (k, v) in account_args.iter().chain(&options.placeholders) {
                 *i = i.clone().replace(k.as_str(), v.as_str());
             }
         }
@@ -121,9 +438,9 @@ impl <'a> MinecraftInstallation<'a> {
         return args;
     }
 
-    fn gen_classpath(&self) -> Vec<OsString> {
+    pub(crate) fn gen_classpath(&self) -> Vec<OsString> {
         let mut ret: Vec<OsString> = vec![];
-        for lib in &self.obj.get_base().libraries {
+        for lib in &self.launcher.apply_library_overrides(&self.obj.get_base().libraries) {
             if !check_rules(&lib.get_base().rules) {
                 continue;
             }
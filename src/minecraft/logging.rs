@@ -0,0 +1,144 @@
+//! Things about the game log.
+//! The game prints Log4j XML events to stdout when the logging config in [super::schemas::VersionJSONLogging] is used.
+
+use std::{str::FromStr, time::{Duration, SystemTime, UNIX_EPOCH}};
+
+use super::process::GameOutput;
+
+/// The level of a [LogRecord].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[allow(missing_docs)]
+pub enum LogLevel {
+    Trace,
+    Debug,
+    Info,
+    Warn,
+    Error,
+    Fatal
+}
+
+impl FromStr for LogLevel {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_uppercase().as_str() {
+            "TRACE" => Ok(Self::Trace),
+            "DEBUG" => Ok(Self::Debug),
+            "INFO" => Ok(Self::Info),
+            "WARN" => Ok(Self::Warn),
+            "ERROR" => Ok(Self::Error),
+            "FATAL" => Ok(Self::Fatal),
+            _ => Err(())
+        }
+    }
+}
+
+/// A structured log record parsed from a Log4j XML event.
+#[derive(Debug, Clone)]
+pub struct LogRecord {
+    /// When the record is logged.
+    pub timestamp: SystemTime,
+    /// The level.
+    pub level: LogLevel,
+    /// The thread name.
+    pub thread: String,
+    /// The logger name.
+    pub logger: String,
+    /// The message.
+    pub message: String,
+    /// The stack trace, if there is one.
+    pub throwable: Option<String>
+}
+
+/// A parsed line of the game output.
+#[derive(Debug, Clone)]
+pub enum LogLine {
+    /// A Log4j event.
+    Record(LogRecord),
+    /// Text that isn't a Log4j event, like things printed by `System.out.println`.
+    Text(GameOutput)
+}
+
+/// Parses Log4j XML events from game output lines.
+/// Events may span many lines, so feed every line in order.
+#[derive(Debug, Default)]
+pub struct Log4jParser {
+    buffer: Option<String>
+}
+
+impl Log4jParser {
+    /// Create a new [Log4jParser].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Feed a line.
+    /// Returns [None] if the line is a part of an unfinished event.
+    pub fn feed(&mut self, line: GameOutput) -> Option<LogLine> {
+        let GameOutput::Stdout(text) = &line else {
+            return Some(LogLine::Text(line));
+        };
+        let buffer = match &mut self.buffer {
+            Some(buffer) => {
+                buffer.push('\n');
+                buffer.push_str(text);
+                buffer
+            },
+            None if text.trim_start().starts_with("<log4j:Event") => self.buffer.insert(text.to_string()),
+            None => return Some(LogLine::Text(line))
+        };
+        if !buffer.trim_end().ends_with("</log4j:Event>") {
+            return None;
+        }
+        let event = self.buffer.take().unwrap();
+        Some(parse_event(&event).map_or(LogLine::Text(GameOutput::Stdout(event)), LogLine::Record))
+    }
+
+    /// Finish parsing when the output ends, like after the game crashes.
+    /// Returns the unfinished event as text, if there is one.
+    pub fn finish(&mut self) -> Option<LogLine> {
+        self.buffer.take().map(|event|LogLine::Text(GameOutput::Stdout(event)))
+    }
+}
+
+fn parse_event(event: &str) -> Option<LogRecord> {
+    let start = event.find("<log4j:Event")?;
+    let head_end = start + event[start..].find('>')?;
+    let head = &event[start + "<log4j:Event".len()..head_end];
+    let timestamp: u64 = get_attribute(head, "timestamp")?.parse().ok()?;
+    Some(LogRecord {
+        timestamp: UNIX_EPOCH + Duration::from_millis(timestamp),
+        level: get_attribute(head, "level")?.parse().ok()?,
+        thread: get_attribute(head, "thread").unwrap_or_default(),
+        logger: get_attribute(head, "logger").unwrap_or_default(),
+        message: get_element(&event[head_end..], "log4j:Message").unwrap_or_default(),
+        throwable: get_element(&event[head_end..], "log4j:Throwable")
+    })
+}
+
+fn get_attribute(head: &str, name: &str) -> Option<String> {
+    let pattern = format!(" {name}=\"");
+    let start = head.find(&pattern)? + pattern.len();
+    let len = head[start..].find('"')?;
+    Some(unescape(&head[start..start + len]))
+}
+
+fn get_element(body: &str, name: &str) -> Option<String> {
+    let open = format!("<{name}>");
+    let start = body.find(&open)? + open.len();
+    let len = body[start..].find(&format!("</{name}>"))?;
+    let content = &body[start..start + len];
+    if let Some(cdata) = content.trim().strip_prefix("<![CDATA[") && let Some(cdata) = cdata.strip_suffix("]]>") {
+        Some(cdata.to_string())
+    } else {
+        Some(unescape(content))
+    }
+}
+
+fn unescape(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
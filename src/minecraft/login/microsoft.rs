@@ -4,6 +4,7 @@ use std::{collections::HashMap, ffi::OsString, fmt::Display};
 
 use anyhow::{anyhow, Ok, Result};
 use async_trait::async_trait;
+use reqwest::Method;
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
 use uuid::Uuid;
@@ -69,9 +70,9 @@ impl AccountConstructor for MicrosoftAccountConstructor {
 
 impl MicrosoftAccount {
     async fn refresh(&mut self, launcher: &LauncherContext) -> Result<()> {
-        let at: Value = launcher.http_client.post("https://login.microsoftonline.com/consumers/oauth2/v2.0/token")
-            .form(&[("client_id", launcher.ms_client_id.as_str()), ("grant_type", "refresh_token"), ("refresh_token", &self.data.as_ref().unwrap().refresh_token)])
-            .send().await?.json().await?;
+        let at: Value = launcher.send(Method::POST, "https://login.microsoftonline.com/consumers/oauth2/v2.0/token", |v|v
+            .form(&[("client_id", launcher.ms_client_id.as_str()), ("grant_type", "refresh_token"), ("refresh_token", &self.data.as_ref().unwrap().refresh_token)]))
+            .await?.json().await?;
         let refresh_token = at["refresh_token"].as_str().clone().unwrap().to_string();
         let next = self.next_steps(at["access_token"].as_str().unwrap(), &launcher).await?;
         self.data = Some(MicrosoftAccountData {
@@ -96,10 +97,9 @@ impl MicrosoftAccount {
                 "TokenType": "JWT"
             }
         );
-        let xbl_res: Value = launcher.http_client
-            .post("https://user.auth.xboxlive.com/user/authenticate")
-            .json(&xbl_req)
-            .send().await?.json().await?;
+        let xbl_res: Value = launcher
+            .send(Method::POST, "https://user.auth.xboxlive.com/user/authenticate", |v|v.json(&xbl_req))
+            .await?.json().await?;
         let xbl_token = xbl_res["Token"].as_str()
             .ok_or(anyhow!("Token in XBL Response isn't a string"))?; // TODO: i18n
         let xbl_uhs = xbl_res["DisplayClaims"]["xui"][0]["uhs"].as_str()
@@ -116,10 +116,9 @@ impl MicrosoftAccount {
                 "TokenType": "JWT"
             }
         );
-        let xsts_res: Value = launcher.http_client
-            .post("https://xsts.auth.xboxlive.com/xsts/authorize")
-            .json(&xsts_req)
-            .send().await?.json().await?;
+        let xsts_res: Value = launcher
+            .send(Method::POST, "https://xsts.auth.xboxlive.com/xsts/authorize", |v|v.json(&xsts_req))
+            .await?.json().await?;
         let xsts_token = xsts_res["Token"].as_str()
             .ok_or(anyhow!("Token in XSTS Response isn't a string"))?; // TODO: i18n
 
@@ -129,18 +128,17 @@ impl MicrosoftAccount {
                 "identityToken": format!("XBL3.0 x={xbl_uhs};{xsts_token}")
             }
         );
-        let mclogin_res: Value = launcher.http_client
-            .post("https://api.minecraftservices.com/authentication/login_with_xbox")
-            .json(&mclogin_req)
-            .send().await?.json().await?;
+        let mclogin_res: Value = launcher
+            .send(Method::POST, "https://api.minecraftservices.com/authentication/login_with_xbox", |v|v.json(&mclogin_req))
+            .await?.json().await?;
         let mclogin_at = mclogin_res["access_token"].as_str()
             .ok_or(anyhow!("Access token in MC Login Response isn't a string"))?; // TODO: i18n
 
         // We skip checking the ownership for XBox Game Pass...
         // MC Data
-        let mcdata_res: Value = launcher.http_client.get("https://api.minecraftservices.com/minecraft/profile")
-            .bearer_auth(mclogin_at)
-            .send().await?.json().await?;
+        let mcdata_res: Value = launcher
+            .send(Method::GET, "https://api.minecraftservices.com/minecraft/profile", |v|v.bearer_auth(mclogin_at))
+            .await?.json().await?;
         if mcdata_res["error"].is_string() {
             return Err(anyhow!(t!("accounts.microsoft.no_minecraft_in_account"))); // TODO: i18n
         }
@@ -164,12 +162,11 @@ impl MicrosoftAccount {
             if estimated >= expires_in {
                 return Result::Err(anyhow!(t!("accounts.microsoft.timeout")).into());
             }
-            let dev_flow_res: Step1ResponseLoop = launcher.http_client.post("https://login.microsoftonline.com/consumers/oauth2/v2.0/token")
-                .form(&[
+            let dev_flow_res: Step1ResponseLoop = launcher.send(Method::POST, "https://login.microsoftonline.com/consumers/oauth2/v2.0/token", |v|v.form(&[
                     ("grant_type", "urn:ietf:params:oauth:grant-type:device_code"),
                     ("code", &flow.device_code),
                     ("client_id", &launcher.ms_client_id)
-                ]).send().await?.json().await?;
+                ])).await?.json().await?;
             if let Step1ResponseLoop::Error { error } = dev_flow_res {
                 match error.as_str() {
                     "expired_token" => return Result::Err(anyhow!(t!("accounts.microsoft.timeout")).into()),
@@ -206,10 +203,10 @@ impl Account for MicrosoftAccount {
     }
 
     async fn login(&mut self, launcher: &LauncherContext) -> Result<()> {
-        let dev_flow: DeviceAuthorizationResponse = launcher.http_client
-            .post("https://login.microsoftonline.com/consumers/oauth2/v2.0/devicecode")
-            .form(&[("client_id", launcher.ms_client_id.as_str()), ("scope", SCOPE)])
-            .send().await?.json().await?;
+        let dev_flow: DeviceAuthorizationResponse = launcher
+            .send(Method::POST, "https://login.microsoftonline.com/consumers/oauth2/v2.0/devicecode", |v|v
+                .form(&[("client_id", launcher.ms_client_id.as_str()), ("scope", SCOPE)]))
+            .await?.json().await?;
         launcher.ui.info(&t!("accounts.microsoft.message", url = dev_flow.verification_uri, code = dev_flow.user_code), "MSA Login").await; // TODO: i18n
         let _ = open::that(&dev_flow.verification_uri);
         let dev_flow_res = self.loop_for_auth(dev_flow, &launcher).await?;
@@ -7,7 +7,7 @@ use std::{collections::HashMap, ffi::OsString, fmt::Display};
 
 use anyhow::{anyhow, Result};
 use async_trait::async_trait;
-use reqwest::StatusCode;
+use reqwest::{Method, StatusCode};
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
 use uuid::Uuid;
@@ -84,15 +84,12 @@ impl <T: YggdrasilAccount> Account for T {
             return false;
         }
         let api_url = api_url.unwrap();
-        let http = &(launcher.http_client);
         let data = self.get_data().as_ref().unwrap();
         let req: Value = json!({
             "accessToken": data.at,
             "clientToken": data.client_token
         });
-        let res = http.post(format!("{api_url}/authserver/validate"))
-            .json(&req)
-            .send().await;
+        let res = launcher.send(Method::POST, &format!("{api_url}/authserver/validate"), |v|v.json(&req)).await;
         res.is_ok() && res.unwrap().status() == StatusCode::NO_CONTENT
     }
 
@@ -102,9 +99,8 @@ impl <T: YggdrasilAccount> Account for T {
             ("password", "Password")
         ], None).await.ok_or(anyhow!("User cancelled"))?; // TODO: i18n
         let api_url = self.get_api_url(&launcher).await?;
-        let http = &launcher.http_client;
 
-        let meta: Value = http.get(&api_url).send().await?.json().await?;
+        let meta: Value = launcher.get(&api_url).await?.json().await?;
         let server_name = meta["meta"]["serverName"].as_str().unwrap().to_string();
 
         let auth_req = json!({
@@ -116,9 +112,7 @@ impl <T: YggdrasilAccount> Account for T {
                 "version": 1
             }
         });
-        let auth_res = http.post(format!("{api_url}/authserver/authenticate"))
-            .json(&auth_req)
-            .send().await?;
+        let auth_res = launcher.send(Method::POST, &format!("{api_url}/authserver/authenticate"), |v|v.json(&auth_req)).await?;
         if auth_res.status().is_client_error() {
             return Err(anyhow!("Yggdrasil auth returned error code {}", auth_res.status())); // TODO: i18n
         }
@@ -1,13 +1,12 @@
-use std::{ffi::OsString, fmt::Display, marker::PhantomData};
+use std::{collections::BTreeMap, ffi::OsString, fmt::Display};
 
 use anyhow::{anyhow, Result};
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use sha2::Sha256;
 use base64::prelude::*;
 
-use crate::{minecraft::{login::{Account, AccountConstructor}, version::MinecraftInstallation}, utils::{check_hash, download, BetterPath}, LauncherContext};
+use crate::{minecraft::{login::{Account, AccountConstructor}, schemas::Resource, version::MinecraftInstallation}, utils::{BetterPath, HashAlgorithm}, LauncherContext};
 
 use super::{YggdrasilAccount, YggdrasilUserData};
 
@@ -61,7 +60,7 @@ impl YggdrasilAccount for AuthlibInjectorAccount {
 
     async fn ask_api_url(&mut self, launcher: &LauncherContext) -> Result<String> {
         let api_url = launcher.ui.ask_user_one(&t!("accounts.authlib_injector.apiurl"), None).await.ok_or(anyhow!("User cancelled"))?; // TODO: i18n
-        let res = launcher.http_client.get(&api_url).send().await;
+        let res = launcher.get(&api_url).await;
         if res.is_err() {
             return Ok(api_url);
         }
@@ -73,18 +72,22 @@ impl YggdrasilAccount for AuthlibInjectorAccount {
 
     async fn prepare_launch(&self, version_launch_dir: &BetterPath, launcher: &LauncherContext) -> Result<()> {
         let path = version_launch_dir / "authlib-injector-latest.jar";
-        let release_info: Value = launcher.http_client
-            .get("https://bmclapi2.bangbang93.com/mirrors/authlib-injector/artifact/latest.json")
-            .send().await?.json().await?;
-        if check_hash(&path, release_info["checksums"]["sha256"].as_str().ok_or(anyhow!("No sha256 in checksums."))?, 0, PhantomData::<Sha256>).await { // TODO: i18n
-            return Ok(());
-        }
-        download(release_info["download_url"].as_str().ok_or(anyhow!("Invaild download URL"))?, &path).await?; // TODO: i18n
-        Ok(())
+        let release_info: Value = launcher
+            .get("https://authlib-injector.yushi.moe/artifact/latest.json").await?
+            .json().await?;
+        let res = Resource {
+            url: release_info["download_url"].as_str().ok_or(anyhow!("Invaild download URL"))?.to_string(), // TODO: i18n
+            hashes: BTreeMap::from([(
+                HashAlgorithm::Sha256,
+                release_info["checksums"]["sha256"].as_str().ok_or(anyhow!("No sha256 in checksums."))?.to_string() // TODO: i18n
+            )]),
+            ..Default::default()
+        };
+        launcher.download_res(&res, &path).await
     }
 
     async fn get_launch_jvmargs(&self, _mc: &MinecraftInstallation, launcher: &LauncherContext) -> Result<Vec<OsString>> {
-        let content = launcher.http_client.get(self.data.as_ref().unwrap().api_url.clone()).send().await?.bytes().await?;
+        let content = launcher.get(&self.data.as_ref().unwrap().api_url).await?.bytes().await?;
         Ok(vec![
             OsString::from(format!("-javaagent:./authlib-injector-latest.jar={}", self.data.as_ref().unwrap().api_url)),
             OsString::from(format!("-Dauthlibinjector.yggdrasil.prefetched={}", BASE64_STANDARD.encode(content)))
@@ -5,7 +5,7 @@ use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 use tokio::fs;
 
-use crate::{minecraft::{login::{Account, AccountConstructor}, version::MinecraftInstallation}, utils::{download, BetterPath}, LauncherContext};
+use crate::{minecraft::{login::{Account, AccountConstructor}, version::MinecraftInstallation}, utils::BetterPath, LauncherContext};
 
 use super::{YggdrasilAccount, YggdrasilUserData};
 
@@ -66,10 +66,10 @@ impl YggdrasilAccount for MinecraftUniversalLoginAccount {
         Ok(format!("https://auth.mc-user.com:233/{}", self.server_id.as_ref().unwrap()))
     }
 
-    async fn prepare_launch(&self, version_launch_dir: &BetterPath, _: &LauncherContext) -> Result<()> {
+    async fn prepare_launch(&self, version_launch_dir: &BetterPath, launcher: &LauncherContext) -> Result<()> {
         let path = version_launch_dir / "nide8auth.jar";
         if fs::metadata(&*path).await.is_err() {
-            download("https://login.mc-user.com:233/index/jar", path.as_ref()).await?;
+            launcher.download("https://login.mc-user.com:233/index/jar", path.as_ref()).await?;
         }
         Ok(())
     }
@@ -0,0 +1,129 @@
+//! Things about planning launches without side effects.
+
+use std::{collections::HashMap, ffi::OsString, path::PathBuf};
+
+use anyhow::Result;
+
+use crate::utils::{check_res, BetterPath};
+
+use super::{launch::{JavaChoice, LaunchOptions}, login::Account, schemas::{Resource, VersionJSON}, version::MinecraftInstallation};
+
+/// Something the account will do when launching.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccountAction {
+    /// [Account::login], because the account isn't initialized.
+    Login,
+    /// [Account::check], which may refresh the token. [Account::login] is called if it fails.
+    Check,
+    /// [Account::prepare_launch], which may download files like authlib-injector.
+    PrepareLaunch,
+    /// [Account::get_launch_jvmargs]. These arguments are not in [LaunchPlan::jvm_arguments].
+    JvmArguments,
+    /// [Account::get_launch_game_args]. Their placeholders like `${auth_access_token}` are kept in [LaunchPlan::game_arguments].
+    GameArguments
+}
+
+/// An entry of the classpath.
+#[derive(Debug, Clone)]
+pub struct ClasspathEntry {
+    /// The path.
+    pub path: BetterPath,
+    /// Whether the file exists now.
+    pub exists: bool
+}
+
+/// What will happen when launching, from [MinecraftInstallation::plan_launch].
+#[derive(Debug, Clone)]
+pub struct LaunchPlan {
+    /// The Java that will be used.
+    /// Javas on the system without `release` files aren't considered, as they can only be probed by running them.
+    pub java: JavaChoice,
+    /// The work dir.
+    pub work_dir: BetterPath,
+    /// The [super::version::DMCLCExtraData::before_command].
+    pub before_command: Option<String>,
+    /// The [super::version::DMCLCExtraData::wrapper_command].
+    pub wrapper_command: Option<String>,
+    /// The [super::version::DMCLCExtraData::after_command].
+    pub after_command: Option<String>,
+    /// The [super::version::DMCLCExtraData::environment].
+    pub environment: HashMap<String, String>,
+    /// The files that will be downloaded.
+    pub downloads: Vec<(Resource, BetterPath)>,
+    /// Whether the assets are unknown because the assets index isn't downloaded.
+    /// The index itself is in [Self::downloads] then.
+    pub assets_unknown: bool,
+    /// The native archives that will be extracted to [Self::natives_directory].
+    pub natives: Vec<BetterPath>,
+    /// Where natives are extracted to.
+    pub natives_directory: BetterPath,
+    /// The classpath.
+    pub classpath: Vec<ClasspathEntry>,
+    /// The JVM arguments.
+    pub jvm_arguments: Vec<OsString>,
+    /// The main class.
+    pub main_class: String,
+    /// The game arguments.
+    pub game_arguments: Vec<OsString>,
+    /// What the account will do.
+    pub account_actions: Vec<AccountAction>
+}
+
+impl MinecraftInstallation<'_> {
+    /// Plan a launch without writing anything or accessing network.
+    /// No Java is run either, so Javas are only found by their `release` files,
+    /// and the GC preset is chosen for the required Java if the chosen one doesn't have one.
+    /// Account related arguments are not resolved, see [AccountAction].
+    pub async fn plan_launch(&self, account: &dyn Account, options: &LaunchOptions) -> Result<LaunchPlan> {
+        let mut account_actions = vec![];
+        let mut account_args = HashMap::new();
+        if account.is_initialized() {
+            account_actions.push(AccountAction::Check);
+            account_args.insert("${auth_uuid}".to_string(), account.get_uuid().simple().to_string());
+        } else {
+            account_actions.push(AccountAction::Login);
+        }
+        account_actions.push(AccountAction::PrepareLaunch);
+        if let VersionJSON::New { .. } = &self.obj {
+            account_actions.push(AccountAction::JvmArguments);
+        }
+        account_actions.push(AccountAction::GameArguments);
+
+        let index = self.read_assets_index().await?;
+        let mut files = self.get_files(false, false, index.as_ref())?;
+        if index.is_none() {
+            files.push((self.obj.get_base().asset_index.res.res.clone(), self.get_assets_index_path()));
+        }
+        let mut downloads = vec![];
+        for (res, path) in files {
+            if !check_res(&res, &path).await {
+                downloads.push((res, path));
+            }
+        }
+
+        let java = self.choose_java_with(false).await;
+        let java_major_version = java.major_version(&self.obj.get_base().java_version, false).await;
+        let (jvm_arguments, game_arguments) = self.gen_args(options, vec![], &account_args, index.as_ref(), java_major_version)?;
+        Ok(LaunchPlan {
+            java,
+            work_dir: self.version_launch_work_dir.clone(),
+            before_command: self.extra_data.before_command.clone(),
+            wrapper_command: self.extra_data.wrapper_command.clone(),
+            after_command: self.extra_data.after_command.clone(),
+            environment: self.extra_data.environment.clone(),
+            downloads,
+            assets_unknown: index.is_none(),
+            natives: self.get_native_archives(),
+            natives_directory: *(&self.version_root / "natives"),
+            classpath: self.gen_classpath().into_iter().map(|v|{
+                let path = BetterPath(PathBuf::from(v));
+                let exists = path.0.is_file();
+                ClasspathEntry { path, exists }
+            }).collect(),
+            jvm_arguments,
+            main_class: self.obj.get_base().main_class.clone(),
+            game_arguments,
+            account_actions
+        })
+    }
+}
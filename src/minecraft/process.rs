@@ -0,0 +1,224 @@
+//! Things about running game processes.
+
+use std::{process::{ExitStatus, Stdio}, sync::Arc};
+
+use anyhow::{anyhow, Result};
+use tokio::{fs, io::{AsyncBufReadExt, AsyncRead, AsyncWriteExt, BufReader}, process::{Child, ChildStdin, Command}, sync::mpsc};
+
+use crate::utils::BetterPath;
+
+use super::logging::{Log4jParser, LogLine};
+
+/// Replaces secrets like access tokens in logs with `***`.
+#[derive(Debug, Clone, Default)]
+pub struct LogMasker {
+    masks: Vec<String>
+}
+
+impl LogMasker {
+    /// Create a [LogMasker] from masks, usually from [super::login::Account::get_log_masks].
+    pub fn new(masks: Vec<String>) -> Self {
+        let mut masks: Vec<String> = masks.into_iter().filter(|v|!v.is_empty()).collect();
+        // Longer masks first, so that a mask containing another one is fully replaced.
+        masks.sort_by_key(|v|std::cmp::Reverse(v.len()));
+        masks.dedup();
+        Self { masks }
+    }
+
+    /// Mask a string.
+    pub fn mask(&self, text: &str) -> String {
+        let mut ret = text.to_string();
+        for i in &self.masks {
+            ret = ret.replace(i.as_str(), "***");
+        }
+        ret
+    }
+
+    pub(crate) fn masks(&self) -> &[String] {
+        &self.masks
+    }
+
+    /// Mask the file at `source` and write it to `dest`.
+    /// `source` and `dest` can be the same.
+    pub async fn mask_file(&self, source: &BetterPath, dest: &BetterPath) -> Result<()> {
+        let content = fs::read(source).await?;
+        fs::write(dest, self.mask(&String::from_utf8_lossy(&content))).await?;
+        Ok(())
+    }
+}
+
+/// A line from the output of a [GameProcess].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GameOutput {
+    /// A line from stdout.
+    Stdout(String),
+    /// A line from stderr.
+    Stderr(String)
+}
+
+impl GameOutput {
+    /// Get the content of the line.
+    pub fn line(&self) -> &str {
+        match self {
+            GameOutput::Stdout(l) => l,
+            GameOutput::Stderr(l) => l
+        }
+    }
+}
+
+/// A handle of a running game.
+///
+/// Output lines are read in the background, so it's fine not to read them.
+/// Secrets in output lines are masked by the [LogMasker].
+/// If it's dropped before the exit is noticed, the game keeps running, and [super::version::DMCLCExtraData::after_command] is run in background when it exits.
+pub struct GameProcess {
+    /// It's only [None] after dropping.
+    child: Option<Child>,
+    output: Option<mpsc::UnboundedReceiver<GameOutput>>,
+    masker: Arc<LogMasker>,
+    parser: Log4jParser,
+    after_exit: Option<Command>,
+    stdin: Option<ChildStdin>
+}
+
+impl GameProcess {
+    /// Spawn the `command` with piped stdout and stderr.
+    /// Stdin is kept as the `command` sets. If it's piped, use [Self::write_line].
+    /// `after_exit` is run when the exit is noticed.
+    pub(crate) fn spawn(command: &mut Command, masker: LogMasker, after_exit: Option<Command>) -> Result<Self> {
+        let mut child = command
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+        let masker = Arc::new(masker);
+        let (tx, rx) = mpsc::unbounded_channel();
+        if let Some(stdout) = child.stdout.take() {
+            tokio::spawn(read_lines(stdout, tx.clone(), masker.clone(), GameOutput::Stdout));
+        }
+        if let Some(stderr) = child.stderr.take() {
+            tokio::spawn(read_lines(stderr, tx, masker.clone(), GameOutput::Stderr));
+        }
+        Ok(Self {
+            output: Some(rx),
+            masker,
+            parser: Log4jParser::new(),
+            after_exit,
+            stdin: child.stdin.take(),
+            child: Some(child)
+        })
+    }
+
+    fn child(&mut self) -> &mut Child {
+        self.child.as_mut().unwrap()
+    }
+
+    /// Get the [LogMasker] used for this process.
+    /// Use it for the log files you write.
+    pub fn log_masker(&self) -> &LogMasker {
+        &self.masker
+    }
+
+    /// Get the OS-assigned process id.
+    /// Returns [None] if the process has exited.
+    pub fn id(&self) -> Option<u32> {
+        self.child.as_ref().and_then(Child::id)
+    }
+
+    /// Receive the next output line.
+    /// Returns [None] if both stdout and stderr are closed, or the output has been taken by [Self::take_output].
+    pub async fn next_output(&mut self) -> Option<GameOutput> {
+        self.output.as_mut()?.recv().await
+    }
+
+    /// Receive the next output line, and parse Log4j XML events in it.
+    /// Don't mix it with [Self::next_output], or some events may be broken.
+    /// An unfinished event is returned as text after the output is closed.
+    pub async fn next_log(&mut self) -> Option<LogLine> {
+        loop {
+            let Some(line) = self.next_output().await else {
+                return self.parser.finish();
+            };
+            if let Some(log) = self.parser.feed(line) {
+                return Some(log);
+            }
+        }
+    }
+
+    /// Write a line to stdin, like a command to a server.
+    pub async fn write_line(&mut self, line: &str) -> Result<()> {
+        let stdin = self.stdin.as_mut().ok_or(anyhow!(t!("process.no_stdin")))?;
+        stdin.write_all(format!("{line}\n").as_bytes()).await?;
+        stdin.flush().await?;
+        Ok(())
+    }
+
+    /// Take the output receiver, so that it can be read in another task while waiting for the process.
+    pub fn take_output(&mut self) -> Option<mpsc::UnboundedReceiver<GameOutput>> {
+        self.output.take()
+    }
+
+    /// Wait for the process to exit, then run [super::version::DMCLCExtraData::after_command].
+    /// Returns an error if the command fails.
+    pub async fn wait(&mut self) -> Result<ExitStatus> {
+        let status = self.child().wait().await?;
+        self.run_after_exit().await?;
+        Ok(status)
+    }
+
+    /// Returns the exit status if the process has exited.
+    /// [super::version::DMCLCExtraData::after_command] is run in background then.
+    pub fn try_wait(&mut self) -> Result<Option<ExitStatus>> {
+        let status = self.child().try_wait()?;
+        if status.is_some() && let Some(mut command) = self.after_exit.take() {
+            tokio::spawn(async move {
+                let _ = command.status().await;
+            });
+        }
+        Ok(status)
+    }
+
+    /// Kill the process and wait for it to exit, then run [super::version::DMCLCExtraData::after_command].
+    pub async fn kill(&mut self) -> Result<()> {
+        self.child().kill().await?;
+        self.run_after_exit().await
+    }
+
+    async fn run_after_exit(&mut self) -> Result<()> {
+        let Some(mut command) = self.after_exit.take() else {
+            return Ok(());
+        };
+        let status = command.status().await?;
+        if !status.success() {
+            return Err(anyhow!(t!("launch.after_command_failed", status = status)));
+        }
+        Ok(())
+    }
+}
+
+impl Drop for GameProcess {
+    fn drop(&mut self) {
+        if let Some(mut command) = self.after_exit.take()
+            && let Some(mut child) = self.child.take()
+            && let Ok(runtime) = tokio::runtime::Handle::try_current() {
+            runtime.spawn(async move {
+                let _ = child.wait().await;
+                let _ = command.status().await;
+            });
+        }
+    }
+}
+
+async fn read_lines<R: AsyncRead + Unpin>(read: R, channel: mpsc::UnboundedSender<GameOutput>, masker: Arc<LogMasker>, wrap: fn(String) -> GameOutput) {
+    let mut reader = BufReader::new(read);
+    let mut buf = vec![];
+    loop {
+        buf.clear();
+        match reader.read_until(b'\n', &mut buf).await {
+            Ok(0) | Err(_) => break,
+            Ok(_) => {
+                let line = String::from_utf8_lossy(&buf);
+                let _ = channel.send(wrap(masker.mask(line.trim_end_matches(['\r', '\n']))));
+            }
+        }
+    }
+}
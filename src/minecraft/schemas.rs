@@ -1,10 +1,10 @@
 #![doc(hidden)]
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
 use serde::{Deserialize, Serialize};
 
-use crate::utils::maven_coord::ArtifactCoordinate;
+use crate::utils::{get_bits, get_os, maven_coord::ArtifactCoordinate, HashAlgorithm};
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct LatestInfo {
@@ -20,7 +20,9 @@ pub struct VersionInfo {
     pub url: String,
     pub time: String,
     #[serde(rename = "releaseTime")]
-    pub release_time: String
+    pub release_time: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha1: Option<String>
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -33,7 +35,22 @@ pub struct VersionList {
 pub struct Resource {
     pub url: String,
     pub sha1: String,
-    pub size: usize
+    pub size: usize,
+    /// Hashes other than [Self::sha1], like the ones from Modrinth.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub hashes: BTreeMap<HashAlgorithm, String>
+}
+
+impl Resource {
+    /// Get the strongest known hash, which can be [Self::sha1].
+    /// Placeholders like empty strings are ignored.
+    pub fn strongest_hash(&self) -> Option<(HashAlgorithm, &str)> {
+        self.hashes.iter()
+            .rev()
+            .map(|(algorithm, digest)|(*algorithm, digest.as_str()))
+            .chain([(HashAlgorithm::Sha1, self.sha1.as_str())])
+            .find(|(algorithm, digest)|algorithm.is_digest(digest))
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, Default)]
@@ -67,7 +84,7 @@ pub struct VersionJSONLogging {
     pub client: Option<LoggingInfo>
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
 #[serde(rename_all = "snake_case")]
 pub enum VersionType {
     Snapshot,
@@ -171,6 +188,20 @@ impl Library {
             Library::BaseOnly(l) => l,
         }
     }
+
+    pub fn get_override_key(&self) -> String {
+        match self {
+            Library::VanillaNatives(l) => format!("{}:natives", l.base.name),
+            _ => self.get_base().name.to_string()
+        }
+    }
+}
+
+impl LibraryVanillaNatives {
+    pub fn get_native_artifact(&self) -> Option<&LibraryArtifact> {
+        let classifier = self.natives.get(&get_os())?.replace("${arch}", &get_bits());
+        self.downloads.classifiers.get(&classifier)
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -303,7 +334,11 @@ pub struct Asset {
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct AssetsIndex {
-    pub objects: HashMap<String, Asset>
+    pub objects: HashMap<String, Asset>,
+    #[serde(default, rename = "virtual")]
+    pub is_virtual: bool,
+    #[serde(default)]
+    pub map_to_resources: bool
 }
 
 fn returns_true() -> bool {
@@ -0,0 +1,231 @@
+//! Things about exporting launch scripts.
+
+use std::ffi::OsStr;
+
+use anyhow::{anyhow, Result};
+
+use crate::utils::ProgressSink;
+
+use super::{launch::{parse_command, LaunchOptions}, login::Account, process::LogMasker, version::MinecraftInstallation};
+
+/// The type of a launch script.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScriptType {
+    /// A POSIX shell script.
+    Shell,
+    /// A Windows batch file.
+    Batch
+}
+
+impl ScriptType {
+    /// Get the script type for current OS.
+    pub fn current() -> Self {
+        if cfg!(target_os = "windows") {
+            ScriptType::Batch
+        } else {
+            ScriptType::Shell
+        }
+    }
+
+    /// Get the file extension, like `sh`.
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ScriptType::Shell => "sh",
+            ScriptType::Batch => "bat"
+        }
+    }
+}
+
+/// How secrets like access tokens are written in a launch script.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SecretMode {
+    /// Write secrets into the script. Don't share the script!
+    Inline,
+    /// Read secrets from environment variables, see [LaunchScript::secrets].
+    Environment
+}
+
+/// A launch script from [MinecraftInstallation::export_launch_script].
+#[derive(Debug, Clone)]
+pub struct LaunchScript {
+    /// The type.
+    pub script_type: ScriptType,
+    /// The content. Write it to a file with [ScriptType::extension].
+    pub content: String,
+    /// The environment variables that should be set before running the script, and their current values.
+    /// It's empty with [SecretMode::Inline].
+    pub secrets: Vec<(String, String)>
+}
+
+enum Segment<'a> {
+    Text(&'a str),
+    Secret(usize)
+}
+
+fn secret_name(index: usize) -> String {
+    format!("DMCLC_SECRET_{}", index + 1)
+}
+
+/// Whether `name` can be an environment variable in both sh and batch scripts, like `[A-Za-z_][A-Za-z0-9_]*`.
+fn is_env_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next().is_some_and(|c|c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c|c.is_ascii_alphanumeric() || c == '_')
+}
+
+fn split_secrets<'a>(arg: &'a str, masks: &[String]) -> Vec<Segment<'a>> {
+    let mut ret = vec![];
+    let mut rest = arg;
+    // Masks are sorted by length, so the longest one wins at the same position.
+    while let Some((pos, index)) = masks.iter().enumerate()
+        .filter_map(|(i, mask)|rest.find(mask.as_str()).map(|pos|(pos, i)))
+        .min_by_key(|(pos, _)|*pos) {
+        if pos > 0 {
+            ret.push(Segment::Text(&rest[..pos]));
+        }
+        ret.push(Segment::Secret(index));
+        rest = &rest[pos + masks[index].len()..];
+    }
+    if !rest.is_empty() || ret.is_empty() {
+        ret.push(Segment::Text(rest));
+    }
+    ret
+}
+
+/// Fail if `text` has `"`, which can't be escaped in a quoted batch argument.
+/// cmd.exe doesn't treat `\"` as an escape, so it would end the quotes and let `&` or `|` after it run commands.
+fn check_batch_text(text: &str) -> Result<()> {
+    if text.contains('"') {
+        return Err(anyhow!(t!("script.batch_quote")));
+    }
+    Ok(())
+}
+
+fn quote(arg: &str, script_type: ScriptType, masks: &[String]) -> Result<String> {
+    let mut ret = String::new();
+    for segment in split_secrets(arg, masks) {
+        match (script_type, segment) {
+            (ScriptType::Shell, Segment::Text(text)) => {
+                ret.push('\'');
+                ret.push_str(&text.replace('\'', r"'\''"));
+                ret.push('\'');
+            },
+            (ScriptType::Shell, Segment::Secret(index)) => ret.push_str(&format!("\"${{{}}}\"", secret_name(index))),
+            (ScriptType::Batch, Segment::Text(text)) => {
+                check_batch_text(text)?;
+                ret.push_str(&text.replace('%', "%%"));
+            },
+            (ScriptType::Batch, Segment::Secret(index)) => ret.push_str(&format!("%{}%", secret_name(index)))
+        }
+    }
+    if script_type == ScriptType::Batch {
+        // Backslashes before the closing quote are doubled, or Java reads them as an escaped quote.
+        let backslashes = ret.len() - ret.trim_end_matches('\\').len();
+        ret.push_str(&"\\".repeat(backslashes));
+        ret = format!("\"{ret}\"");
+    }
+    Ok(ret)
+}
+
+impl MinecraftInstallation<'_> {
+    /// Export a script that launches the game without the launcher.
+    /// It contains the Java from [Self::get_java], the work dir, the arguments from [Self::launch_args],
+    /// and the commands and environment variables in [super::version::DMCLCExtraData].
+    /// So the account may login, and missing files may be downloaded.
+    /// The secrets from [Account::get_log_masks] are written as the `secret_mode` says.
+    /// It fails if a name in [super::version::DMCLCExtraData::environment] isn't like `[A-Za-z_][A-Za-z0-9_]*`.
+    /// [ScriptType::Batch] also fails if an argument, a path or an environment variable has `"`, as cmd.exe can't escape it.
+    pub async fn export_launch_script(&self, account: &mut dyn Account, options: &LaunchOptions, script_type: ScriptType, secret_mode: SecretMode, progress: &dyn ProgressSink) -> Result<LaunchScript> {
+        if let Some(name) = self.extra_data.environment.keys().find(|v|!is_env_name(v)) {
+            return Err(anyhow!(t!("script.invalid_env_name", name = name)));
+        }
+        let java = self.get_java(progress).await?;
+        let args = self.launch_args(account, options, progress).await?;
+        let masker = LogMasker::new(account.get_log_masks());
+        let masks = match secret_mode {
+            SecretMode::Inline => &[][..],
+            SecretMode::Environment => masker.masks()
+        };
+        let quote = |v: &OsStr|quote(&v.to_string_lossy(), script_type, masks);
+        let cwd = quote(self.get_cwd().as_os_str())?;
+        let java_command = parse_command(&self.extra_data.wrapper_command)?.iter()
+            .map(|v|quote(OsStr::new(v)))
+            .chain([quote(&java)])
+            .collect::<Result<Vec<_>>>()?.join(" ");
+        let mut environment: Vec<_> = self.extra_data.environment.iter().collect();
+        environment.sort();
+        let after_command = &self.extra_data.after_command;
+        let mut lines = vec![];
+        match script_type {
+            ScriptType::Shell => {
+                lines.push("#!/bin/sh".to_string());
+                lines.push(format!("# Launch script of {}.", self.name));
+                for i in 0..masks.len() {
+                    lines.push(format!(": \"${{{}:?}}\"", secret_name(i)));
+                }
+                for (k, v) in &environment {
+                    lines.push(format!("export {k}={}", quote(OsStr::new(v))?));
+                }
+                lines.push(format!("cd {cwd} || exit 1"));
+                if let Some(command) = &self.extra_data.before_command {
+                    lines.push(format!("{command} || exit $?"));
+                }
+                if after_command.is_some() {
+                    lines.push(format!("{java_command} \\"));
+                } else {
+                    lines.push(format!("exec {java_command} \\"));
+                }
+            },
+            ScriptType::Batch => {
+                lines.push("@echo off".to_string());
+                lines.push(format!("rem Launch script of {}.", self.name));
+                lines.push("chcp 65001 > nul".to_string());
+                for i in 0..masks.len() {
+                    let name = secret_name(i);
+                    lines.push(format!("if not defined {name} (echo {name} is not set & exit /b 1)"));
+                }
+                for (k, v) in &environment {
+                    check_batch_text(v)?;
+                    lines.push(format!("set \"{k}={}\"", v.replace('%', "%%")));
+                }
+                lines.push(format!("cd /d {cwd}"));
+                if let Some(command) = &self.extra_data.before_command {
+                    lines.push(command.clone());
+                    lines.push("if errorlevel 1 exit /b %errorlevel%".to_string());
+                }
+                lines.push(format!("{java_command} ^"));
+            }
+        }
+        let continuation = match script_type {
+            ScriptType::Shell => " \\",
+            ScriptType::Batch => " ^"
+        };
+        for (i, arg) in args.iter().enumerate() {
+            let end = if i + 1 == args.len() { "" } else { continuation };
+            lines.push(format!("  {}{end}", quote(arg)?));
+        }
+        if let Some(command) = after_command {
+            match script_type {
+                ScriptType::Shell => {
+                    lines.push("status=$?".to_string());
+                    lines.push(command.clone());
+                    lines.push("exit $status".to_string());
+                },
+                ScriptType::Batch => {
+                    lines.push("set DMCLC_STATUS=%errorlevel%".to_string());
+                    lines.push(command.clone());
+                    lines.push("exit /b %DMCLC_STATUS%".to_string());
+                }
+            }
+        }
+        let newline = match script_type {
+            ScriptType::Shell => "\n",
+            ScriptType::Batch => "\r\n"
+        };
+        Ok(LaunchScript {
+            script_type,
+            content: lines.join(newline) + newline,
+            secrets: masks.iter().enumerate().map(|(i, v)|(secret_name(i), v.clone())).collect()
+        })
+    }
+}
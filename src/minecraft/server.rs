@@ -0,0 +1,235 @@
+//! Things about dedicated servers.
+
+use std::{collections::HashMap, ffi::OsString, path::PathBuf, process::Stdio};
+
+use anyhow::{anyhow, Ok, Result};
+use serde::{Deserialize, Serialize};
+use tokio::{fs, process::Command};
+
+use crate::{java::tuning::{get_tuning_args, GCPreset, MemorySettings}, utils::{download_all, DownloadPhase, BetterPath, ProgressSink, PATH_DELIMITER}, LauncherContext};
+
+use super::{install::VersionInfo, launch::JavaChoice, process::{GameProcess, LogMasker}, schemas::{JavaInfo, VersionJSON}, version::ComponentInfo, instance::{check_name, remove_on_error}};
+
+const SERVER_DATA_FILE: &str = "dmclc_server.json";
+
+/// How a server is started.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase", tag = "type")]
+pub enum ServerLaunchTarget {
+    /// Run a jar with `-jar`, used by vanilla and older Forge.
+    Jar {
+        /// The path relative to the server directory.
+        path: String
+    },
+    /// Read arguments from a file with `@`, used by newer Forge and NeoForge.
+    ArgsFile {
+        /// The path relative to the server directory.
+        path: String
+    },
+    /// Run a main class with a classpath, used by Fabric and Quilt.
+    #[serde(rename_all = "camelCase")]
+    MainClass {
+        /// The main class.
+        main_class: String,
+        /// The classpath.
+        classpath: Vec<String>,
+        /// JVM arguments before the main class.
+        jvm_arguments: Vec<String>
+    }
+}
+
+fn default_server_arguments() -> Vec<String> {
+    vec!["nogui".to_string()]
+}
+
+/// Some extra datas of a server.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ServerExtraData {
+    /// Stores Minecraft version.
+    pub version: String,
+    /// Stores the Java requirement of the Minecraft version.
+    pub java_version: JavaInfo,
+    /// Stores how the server is started.
+    pub launch_target: ServerLaunchTarget,
+    /// Stores components list.
+    #[serde(default)]
+    pub components: Vec<ComponentInfo>,
+    /// Stores the java command that should be used.
+    /// If it's [None], it's chosen like [super::version::MinecraftInstallation::choose_java].
+    pub with_java: Option<String>,
+    /// Stores heap size settings.
+    #[serde(default)]
+    pub memory: MemorySettings,
+    /// Stores the garbage collector preset.
+    pub gc_preset: Option<GCPreset>,
+    /// Stores appended java arguments.
+    #[serde(default)]
+    pub extra_jvm_arguments: Vec<String>,
+    /// Stores the server arguments. It's `nogui` by default.
+    #[serde(default = "default_server_arguments")]
+    pub server_arguments: Vec<String>,
+    /// Stores the environment variables of the server.
+    #[serde(default)]
+    pub environment: HashMap<String, String>
+}
+
+/// Represents a dedicated server in `servers/<name>`.
+pub struct ServerInstallation<'l> {
+    /// Some extra datas.
+    pub extra_data: ServerExtraData,
+    pub(crate) launcher: &'l LauncherContext,
+    pub(crate) name: String,
+    pub(crate) root: BetterPath
+}
+
+impl VersionInfo {
+    /// Install a dedicated server.
+    /// It fails if the `name` is invalid or the server already exists.
+    pub async fn install_server<'l>(&self, launcher: &'l LauncherContext, name: &str, progress: &dyn ProgressSink) -> Result<ServerInstallation<'l>> {
+        check_name(name)?;
+        let root = *(&launcher.root_path / "servers" / name);
+        if fs::try_exists(&root).await? {
+            return Err(anyhow!(t!("server.already_exists", name = name)));
+        }
+        let obj: VersionJSON = serde_json::from_str(&self.get_json(launcher).await?)?;
+        fs::create_dir_all(&root).await?;
+        let result = download_all(
+            &vec![(obj.get_base().downloads.server.clone(), *(&root / "server.jar"))], DownloadPhase::Server, progress,
+            launcher.download_threads_per_file, launcher.download_parallel_files,
+            launcher.download_retries, launcher.mirror_provider.as_ref()
+        ).await;
+        remove_on_error(&root, result.and_then(|v|Ok(v.into_result()?))).await?;
+        let server = ServerInstallation {
+            extra_data: ServerExtraData {
+                version: self.id.clone(),
+                java_version: obj.get_base().java_version.clone(),
+                launch_target: ServerLaunchTarget::Jar { path: "server.jar".to_string() },
+                components: vec![],
+                with_java: None,
+                memory: MemorySettings::default(),
+                gc_preset: None,
+                extra_jvm_arguments: vec![],
+                server_arguments: default_server_arguments(),
+                environment: HashMap::new()
+            },
+            launcher,
+            name: name.to_string(),
+            root
+        };
+        server.save().await?;
+        Ok(server)
+    }
+}
+
+impl LauncherContext {
+    /// List the names of servers in the `root_path`.
+    pub async fn list_servers(&self) -> Result<Vec<String>> {
+        let mut ret = vec![];
+        let Result::Ok(mut entries) = fs::read_dir(&(&self.root_path / "servers")).await else {
+            return Ok(ret);
+        };
+        while let Some(entry) = entries.next_entry().await? {
+            if entry.path().join(SERVER_DATA_FILE).is_file() {
+                ret.push(entry.file_name().to_string_lossy().to_string());
+            }
+        }
+        Ok(ret)
+    }
+
+    /// Get one [ServerInstallation] by name in the `root_path`.
+    pub async fn get_server(&self, name: &str) -> Option<ServerInstallation<'_>> {
+        let root = *(&self.root_path / "servers" / name);
+        let data = fs::read(&root / SERVER_DATA_FILE).await.ok()?;
+        Some(ServerInstallation {
+            extra_data: serde_json::from_slice(&data).ok()?,
+            launcher: self,
+            name: name.to_string(),
+            root
+        })
+    }
+}
+
+impl ServerInstallation<'_> {
+    /// Get the name.
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    /// Get the server directory, which is also the work dir.
+    pub fn get_dir(&self) -> PathBuf {
+        self.root.0.clone()
+    }
+
+    /// Save [Self::extra_data].
+    pub async fn save(&self) -> Result<()> {
+        fs::write(&self.root / SERVER_DATA_FILE, serde_json::to_vec(&self.extra_data)?).await?;
+        Ok(())
+    }
+
+    /// Check if the Minecraft EULA is accepted in `eula.txt`.
+    pub async fn is_eula_accepted(&self) -> bool {
+        fs::read_to_string(&self.root / "eula.txt").await
+            .is_ok_and(|v|v.lines().any(|v|v.trim() == "eula=true"))
+    }
+
+    /// Write `eula.txt` to accept the Minecraft EULA (<https://aka.ms/MinecraftEULA>).
+    /// Only call it after the user agrees to the EULA.
+    pub async fn accept_eula(&self) -> Result<()> {
+        fs::write(&self.root / "eula.txt", "#By changing the setting below to TRUE you are indicating your agreement to our EULA (https://aka.ms/MinecraftEULA).\neula=true\n").await?;
+        Ok(())
+    }
+
+    /// Get the java command that should be used.
+    /// The Java runtime provided by Mojang is installed if missing.
+    pub async fn get_java(&self, progress: &dyn ProgressSink) -> Result<OsString> {
+        Ok(match self.launcher.choose_java(self.extra_data.with_java.as_deref(), &self.extra_data.java_version, true).await {
+            JavaChoice::Configured(java) => java,
+            JavaChoice::Runtime { java: Some(java), .. } => java.0.into_os_string(),
+            JavaChoice::Runtime { component, java: None } => self.launcher.install_java_runtime(&component, progress).await?.0.into_os_string(),
+            JavaChoice::System(java) => java.0.into_os_string(),
+            JavaChoice::Path => OsString::from("java")
+        })
+    }
+
+    /// Generate the launch arguments.
+    /// Please set the work dir to [Self::get_dir].
+    /// The GC preset is chosen for the Java that [Self::get_java] returns.
+    pub async fn launch_args(&self) -> Vec<OsString> {
+        let data = &self.extra_data;
+        let java_major_version = self.launcher.choose_java(data.with_java.as_deref(), &data.java_version, true).await.major_version(&data.java_version, true).await;
+        let mut args = get_tuning_args(&data.memory, data.gc_preset, &(&self.root / "mods"), java_major_version);
+        args.extend(data.extra_jvm_arguments.iter().map(OsString::from));
+        match &data.launch_target {
+            ServerLaunchTarget::Jar { path } => {
+                args.push(OsString::from("-jar"));
+                args.push(OsString::from(path));
+            },
+            ServerLaunchTarget::ArgsFile { path } => args.push(OsString::from(format!("@{path}"))),
+            ServerLaunchTarget::MainClass { main_class, classpath, jvm_arguments } => {
+                args.extend(jvm_arguments.iter().map(OsString::from));
+                args.push(OsString::from("-cp"));
+                args.push(OsString::from(classpath.join(PATH_DELIMITER)));
+                args.push(OsString::from(main_class));
+            }
+        }
+        args.extend(data.server_arguments.iter().map(OsString::from));
+        args
+    }
+
+    /// Start the server.
+    /// Stdin is piped, so commands can be sent by [GameProcess::write_line].
+    /// Remember to accept the EULA by [Self::accept_eula] first, or the server stops at once.
+    pub async fn launch(&self, progress: &dyn ProgressSink) -> Result<GameProcess> {
+        let java = self.get_java(progress).await?;
+        GameProcess::spawn(
+            Command::new(java)
+                .args(self.launch_args().await)
+                .current_dir(self.get_dir())
+                .envs(&self.extra_data.environment)
+                .stdin(Stdio::piped()),
+            LogMasker::default(),
+            None
+        )
+    }
+}
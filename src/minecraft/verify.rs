@@ -0,0 +1,120 @@
+//! Things about checking the integrity of installations.
+
+use anyhow::{Ok, Result};
+use tokio::fs;
+
+use crate::utils::{download_all, DownloadPhase, BetterPath, ProgressSink};
+
+use super::{schemas::Resource, version::MinecraftInstallation};
+
+/// The result of [MinecraftInstallation::verify].
+#[derive(Debug, Clone, Default)]
+pub struct VerifyReport {
+    /// Files that don't exist.
+    pub missing: Vec<(Resource, BetterPath)>,
+    /// Files whose size or hash is wrong.
+    pub corrupt: Vec<(Resource, BetterPath)>,
+    /// Files that exist but can't be checked, because their hash is unknown.
+    pub unverifiable: Vec<(Resource, BetterPath)>,
+    /// Files in `mods` that won't be loaded, because no installed mod loader recognizes them.
+    pub extra_mods: Vec<BetterPath>,
+    /// Whether assets aren't checked, because the assets index is missing or corrupt.
+    /// The index is in [Self::missing] or [Self::corrupt] then, so verify again after [MinecraftInstallation::repair].
+    pub assets_unchecked: bool
+}
+
+impl VerifyReport {
+    /// Whether nothing is missing or corrupt.
+    /// [Self::unverifiable] and [Self::extra_mods] are only warnings.
+    pub fn is_ok(&self) -> bool {
+        self.missing.is_empty() && self.corrupt.is_empty() && !self.assets_unchecked
+    }
+}
+
+enum FileState {
+    Ok,
+    Missing,
+    Corrupt,
+    Unverifiable
+}
+
+async fn check_file(res: &Resource, path: &BetterPath) -> FileState {
+    if !fs::try_exists(path).await.unwrap_or(false) {
+        return FileState::Missing;
+    }
+    match res.strongest_hash() {
+        None => FileState::Unverifiable,
+        Some((algorithm, digest)) if algorithm.check(path, digest, res.size).await => FileState::Ok,
+        Some(_) => FileState::Corrupt
+    }
+}
+
+impl MinecraftInstallation<'_> {
+    /// Check the client jar, libraries, natives, assets and the logging config with their strongest hashes without downloading anything.
+    /// Mods are checked too, see [VerifyReport::extra_mods].
+    pub async fn verify(&self) -> Result<VerifyReport> {
+        let mut report = VerifyReport::default();
+        let index = self.read_assets_index().await?;
+        let mut files = self.get_files(false, true, index.as_ref())?;
+        if index.is_none() {
+            report.assets_unchecked = true;
+            files.push((self.obj.get_base().asset_index.res.res.clone(), self.get_assets_index_path()));
+        }
+        let states = futures_util::future::join_all(files.iter().map(|(res, path)|check_file(res, path))).await;
+        for (file, state) in files.into_iter().zip(states) {
+            match state {
+                FileState::Ok => (),
+                FileState::Missing => report.missing.push(file),
+                FileState::Corrupt => report.corrupt.push(file),
+                FileState::Unverifiable => report.unverifiable.push(file)
+            }
+        }
+        report.extra_mods = self.find_extra_mods().await?;
+        Ok(report)
+    }
+
+    /// Find mod files that no installed mod loader recognizes.
+    /// Without the `mod_loaders` feature, all mods are extra if no component is installed.
+    async fn find_extra_mods(&self) -> Result<Vec<BetterPath>> {
+        let mods_dir = *(&self.version_launch_work_dir / "mods");
+        let mut ret = vec![];
+        #[cfg(feature="mod_loaders")]
+        let recognized = if self.extra_data.components.is_empty() {
+            None
+        } else {
+            self.list_mods().await.ok()
+        };
+        let Result::Ok(mut entries) = fs::read_dir(&mods_dir).await else {
+            return Ok(ret);
+        };
+        while let Some(entry) = entries.next_entry().await? {
+            let name = entry.file_name().to_string_lossy().to_string();
+            if !name.ends_with(".jar") || entry.file_type().await?.is_dir() {
+                continue;
+            }
+            #[cfg(feature="mod_loaders")]
+            let extra = match &recognized {
+                Some(mods) => mods.get(&name).is_some_and(|v|v.is_empty()),
+                None => self.extra_data.components.is_empty()
+            };
+            #[cfg(not(feature="mod_loaders"))]
+            let extra = self.extra_data.components.is_empty();
+            if extra {
+                ret.push(*(&mods_dir / &name));
+            }
+        }
+        Ok(ret)
+    }
+
+    /// Download the missing and corrupt files in a [VerifyReport] from [Self::verify].
+    /// Other files aren't touched. Fails with [crate::utils::DownloadError] if any file can't be downloaded.
+    pub async fn repair(&self, report: &VerifyReport, progress: &dyn ProgressSink) -> Result<()> {
+        let resources: Vec<_> = report.missing.iter().chain(&report.corrupt).cloned().collect();
+        download_all(
+            &resources, DownloadPhase::Repair, progress,
+            self.launcher.download_threads_per_file, self.launcher.download_parallel_files,
+            self.launcher.download_retries, self.launcher.mirror_provider.as_ref()
+        ).await?.into_result()?;
+        Ok(())
+    }
+}
@@ -1,13 +1,16 @@
 //! Things about a Minecraft installation.
 
-use std::{ffi::OsString, fs};
+use std::{collections::HashMap, ffi::OsString, fs};
 
+use anyhow::Result;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
-use crate::{utils::BetterPath, LauncherContext};
+use crate::{java::tuning::{GCPreset, MemorySettings}, utils::BetterPath, LauncherContext};
 
-use super::schemas::VersionJSON;
+use super::{instance::InstanceMetadata, schemas::VersionJSON};
+
+pub(crate) const EXTRA_DATA_FILE: &str = "dmclc_extras.json";
 
 /// Represents a component.
 #[derive(Serialize, Deserialize)]
@@ -27,14 +30,28 @@ pub struct DMCLCExtraData {
     /// Stores components list.
     #[serde(rename = "loaders")]
     pub components: Vec<ComponentInfo>,
+    /// Stores files in `libraries` that components generate instead of downloading, like the outputs of Forge processors.
+    /// They're relative to `libraries`, and kept by [crate::LauncherContext::collect_garbage].
+    /// It's [None] if they're unknown, like for imported versions.
+    #[serde(default)]
+    pub generated_libraries: Option<Vec<String>>,
     /// Stores if independent game dir is enabled.
     #[serde(rename = "enableIndependentGameDir")]
     pub independent_game_dir: bool,
     /// Stores the command that should be executed before launching.
-    /// It's client's responsibility to execute it.
+    /// It's executed by [MinecraftInstallation::launch], and split by [crate::utils::split_command_line].
     pub before_command: Option<String>,
+    /// Stores the wrapper command that java is run with, like `gamemoderun mangohud`.
+    pub wrapper_command: Option<String>,
+    /// Stores the command that should be executed after the game exits.
+    /// It's executed by [super::process::GameProcess::wait], or in background if the [super::process::GameProcess] is dropped.
+    pub after_command: Option<String>,
+    /// Stores the environment variables of the game, which are also set for [Self::before_command], [Self::wrapper_command] and [Self::after_command].
+    #[serde(default)]
+    pub environment: HashMap<String, String>,
     /// Stores the java command that should be used.
-    /// It's client's responsibility to use it.
+    /// If it's [None], the Java runtime provided by Mojang is used.
+    /// See [MinecraftInstallation::get_java].
     #[serde(rename = "usingJava")]
     pub with_java: Option<String>,
     /// Stores appended game arguments.
@@ -42,7 +59,29 @@ pub struct DMCLCExtraData {
     pub extra_game_arguments: Option<Vec<OsString>>,
     /// Stores appended java arguments.
     #[serde(rename = "moreJavaArguments")]
-    pub extra_jvm_arguments: Option<Vec<OsString>>
+    pub extra_jvm_arguments: Option<Vec<OsString>>,
+    /// Stores heap size settings.
+    #[serde(default)]
+    pub memory: MemorySettings,
+    /// Stores the garbage collector preset.
+    #[serde(default)]
+    pub gc_preset: Option<GCPreset>,
+    /// Stores user-facing informations like the display name.
+    #[serde(default)]
+    pub metadata: InstanceMetadata
+}
+
+impl DMCLCExtraData {
+    /// Record files in `libraries` generated by installing a component.
+    /// They stay unknown if files of the components installed before are unknown.
+    #[cfg(feature="components_installation")]
+    pub(crate) fn add_generated_libraries(&mut self, files: Vec<String>) {
+        match &mut self.generated_libraries {
+            Some(generated) => generated.extend(files),
+            None if self.components.is_empty() => self.generated_libraries = Some(files),
+            None => ()
+        }
+    }
 }
 
 /// Represents a Minecraft installation.
@@ -80,11 +119,22 @@ impl <'l> MinecraftInstallation<'l> {
         }
     }
 
+    /// Get the name.
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    /// Save [Self::extra_data].
+    pub async fn save(&self) -> Result<()> {
+        tokio::fs::write(&self.version_root / EXTRA_DATA_FILE, serde_json::to_vec(&self.extra_data)?).await?;
+        Ok(())
+    }
+
     fn get_extras(
         #[cfg(feature="mod_loaders")]
         launcher: &'l LauncherContext,
         version_root: &BetterPath, object: &VersionJSON, independent_game_dir: bool) -> DMCLCExtraData {
-        let path = &*(version_root / "dmclc_extras.json");
+        let path = &*(version_root / EXTRA_DATA_FILE);
         if fs::metadata(path).is_ok() && let Ok(f) = fs::File::open(path) && let Ok(v) = serde_json::from_reader(f) {
             return v;
         }
@@ -101,11 +151,18 @@ impl <'l> MinecraftInstallation<'l> {
         let ret = DMCLCExtraData {
             version,
             components,
+            generated_libraries: None,
             independent_game_dir,
             before_command: None,
+            wrapper_command: None,
+            after_command: None,
+            environment: HashMap::new(),
             with_java: None,
             extra_game_arguments: None,
-            extra_jvm_arguments: None
+            extra_jvm_arguments: None,
+            memory: MemorySettings::default(),
+            gc_preset: None,
+            metadata: InstanceMetadata::default()
         };
         if let Ok(file) = fs::File::create(path) {
             let _ = serde_json::to_writer(file, &ret);
@@ -2,25 +2,38 @@
 
 mod better_path;
 mod download;
+mod hash;
+mod progress;
 pub mod maven_coord;
+pub mod mirror;
 
-use std::ffi::{OsStr, OsString};
+use std::{collections::HashMap, ffi::{OsStr, OsString}, sync::{LazyLock, Mutex}};
 
 use anyhow::{anyhow, Result};
 use maven_coord::ArtifactCoordinate;
+use regex::Regex;
 #[cfg(feature="mod_loaders")]
 use serde::de::Visitor;
 #[cfg(feature="mod_loaders")]
 use serde::Deserializer;
+#[cfg(not(windows))]
+use sysinfo::System;
 #[cfg(feature="mod_loaders")]
 use versions::{Requirement, Versioning};
 
 #[cfg(feature="mod_loaders")]
 use crate::components::mods::VersionBound;
-use crate::minecraft::schemas::{Arguments, EnvRule, OSType, VersionJSON};
+use crate::minecraft::schemas::{Arguments, EnvRule, EnvRuleType, OSType, VersionJSON};
 
 pub use self::better_path::BetterPath;
-pub use self::download::{download, download_res, download_txt, download_all, check_hash, download_to_writer, DownloadAllMessage};
+pub use self::download::{download_all, check_hash, DownloadReport, DownloadFailure, DownloadError};
+pub use self::hash::{HashAlgorithm, check_res};
+pub(crate) use self::hash::verify_downloaded;
+pub use self::progress::{DownloadEvent, DownloadPhase, DownloadProgress, ProgressSink, NoProgress, DownloadControl, Controlled};
+pub(crate) use self::progress::{check_cancelled, cancellable};
+#[cfg(feature="components_installation")]
+pub(crate) use self::progress::wait_child;
+pub use self::mirror::MirrorProvider;
 
 #[cfg(not(target_os="windows"))]
 /// The path delimiter.
@@ -48,6 +61,83 @@ pub fn get_os() -> OSType {
     OSType::OSX
 }
 
+/// Get current architecture in the names used by Mojang and other launchers.
+/// Returns `x86`, `x86_64`, `arm64`, `arm32` or the Rust architecture name for others.
+pub fn get_arch() -> &'static str {
+    match std::env::consts::ARCH {
+        "x86" => "x86",
+        "x86_64" => "x86_64",
+        "aarch64" => "arm64",
+        "arm" => "arm32",
+        other => other
+    }
+}
+
+/// Get current platform name like `linux-arm64`, used by [crate::LauncherContext::load_library_overrides].
+pub fn get_platform() -> String {
+    let os = match get_os() {
+        OSType::Linux => "linux",
+        OSType::Windows => "windows",
+        OSType::OSX => "osx"
+    };
+    format!("{os}-{}", get_arch())
+}
+
+/// Read the Windows version from the registry, like `10.0.19045`.
+#[cfg(windows)]
+fn read_os_version() -> Option<String> {
+    let key = windows_registry::LOCAL_MACHINE.open(r"SOFTWARE\Microsoft\Windows NT\CurrentVersion").ok()?;
+    let build = key.get_string("CurrentBuildNumber").ok()?;
+    // `CurrentVersion` stays `6.3` since Windows 10, which stores the major and minor versions separately.
+    let version = match (key.get_u32("CurrentMajorVersionNumber"), key.get_u32("CurrentMinorVersionNumber")) {
+        (Ok(major), Ok(minor)) => format!("{major}.{minor}"),
+        _ => key.get_string("CurrentVersion").ok()?
+    };
+    Some(format!("{version}.{build}"))
+}
+
+#[cfg(target_os = "macos")]
+fn read_os_version() -> Option<String> {
+    System::os_version()
+}
+
+#[cfg(not(any(windows, target_os = "macos")))]
+fn read_os_version() -> Option<String> {
+    System::kernel_version()
+}
+
+static OS_VERSION: LazyLock<Option<String>> = LazyLock::new(read_os_version);
+
+/// Compiled `os.version` patterns of rules. Invalid ones are [None].
+static RULE_REGEXES: LazyLock<Mutex<HashMap<String, Option<Regex>>>> = LazyLock::new(Default::default);
+
+fn match_os_version(pattern: &str) -> bool {
+    let Some(current) = get_os_version() else {
+        return false;
+    };
+    let mut regexes = RULE_REGEXES.lock().unwrap();
+    regexes.entry(pattern.to_string())
+        .or_insert_with(||Regex::new(pattern).ok())
+        .as_ref()
+        .is_some_and(|regex|regex.is_match(current))
+}
+
+/// Get current OS version, like `10.0.19045` on Windows.
+pub fn get_os_version() -> Option<&'static str> {
+    OS_VERSION.as_deref()
+}
+
+fn check_rule_arch(arch: &str) -> bool {
+    let current = get_arch();
+    match arch {
+        "x86" | "i386" | "i686" => current == "x86",
+        "x86_64" | "amd64" => current == "x86_64",
+        "arm64" | "aarch64" => current == "arm64",
+        "arm32" | "arm" => current == "arm32",
+        other => other == current
+    }
+}
+
 #[cfg(target_pointer_width = "64")]
 /// Get current bits.
 pub fn get_bits() -> String {
@@ -60,38 +150,87 @@ pub fn get_bits() -> String {
     "32".to_string()
 }
 
-/// Check one [EnvRule].
-pub fn check_rule(rule: &EnvRule) -> bool {
+/// Check if one [EnvRule] matches the current environment and the `features`.
+/// The action of the rule is ignored.
+pub fn check_rule(rule: &EnvRule, features: &HashMap<String, bool>) -> bool {
     if let Some(os) = &rule.os {
-        if let Some(os) = &os.name {
-            return get_os() == *os;
+        if let Some(name) = &os.name && get_os() != *name {
+            return false;
+        }
+        if let Some(arch) = &os.arch && !check_rule_arch(arch) {
+            return false;
+        }
+        if let Some(version) = &os.version && !match_os_version(version) {
+            return false;
         }
     }
-
-    rule.features.is_none() // No features support currently
+    if let Some(required) = &rule.features {
+        return required.iter().all(|(k, v)|features.get(k).copied().unwrap_or(false) == *v);
+    }
+    true
 }
 
 /// Check [EnvRule]s.
-pub fn check_rules_no_option(rules: &Vec<EnvRule>) -> bool {
+/// Like Mojang's launcher, the action of the last matching rule is used, and it's disallowed if no rule matches.
+pub fn check_rules_no_option(rules: &Vec<EnvRule>, features: &HashMap<String, bool>) -> bool {
+    let mut allowed = false;
     for i in rules {
-        if !check_rule(i) {
-            return false;
+        if check_rule(i, features) {
+            allowed = matches!(i.action, EnvRuleType::Allow);
         }
     }
-    true
+    allowed
 }
 
-/// Optionally check [EnvRule]s.
+/// Optionally check [EnvRule]s without features, like the rules of libraries.
 pub fn check_rules(rules: &Option<Vec<EnvRule>>) -> bool {
-    if rules.is_none() {
-        return true;
+    if let Some(rules) = rules {
+        check_rules_no_option(rules, &HashMap::new())
+    } else {
+        true
     }
-    for i in rules.as_ref().unwrap() {
-        if check_rule(i) {
-            return true;
+}
+
+/// Parse the major version from a Java version string like `17.0.8+7`, `1.8.0_381` or `21`.
+pub fn parse_java_major_version(version: &str) -> Option<usize> {
+    let mut parts = version.trim().split(|c: char|!c.is_ascii_digit());
+    let first: usize = parts.next()?.parse().ok()?;
+    if first == 1 {
+        parts.next()?.parse().ok()
+    } else {
+        Some(first)
+    }
+}
+
+/// Split a command line into words like a shell does.
+/// Quotes group words, and backslashes escape quotes, backslashes and spaces. Other backslashes are kept, so Windows paths work.
+/// Returns [None] if a quote isn't closed.
+pub fn split_command_line(command: &str) -> Option<Vec<String>> {
+    let mut ret = vec![];
+    let mut current: Option<String> = None;
+    let mut chars = command.chars().peekable();
+    let mut quote: Option<char> = None;
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (None, '\'') | (None, '"') => {
+                current.get_or_insert_default();
+                quote = Some(c);
+            },
+            (Some('\''), c) => current.get_or_insert_default().push(c),
+            (_, '\\') if let Some(&next) = chars.peek() && (next == '"' || next == '\\' || (quote.is_none() && (next == '\'' || next.is_whitespace()))) => {
+                current.get_or_insert_default().push(next);
+                chars.next();
+            },
+            (None, c) if c.is_whitespace() => ret.extend(current.take()),
+            (_, c) => current.get_or_insert_default().push(c)
         }
     }
-    false
+    if quote.is_some() {
+        return None;
+    }
+    ret.extend(current);
+    Some(ret)
 }
 
 /// Concat two OsStr.
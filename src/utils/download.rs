@@ -1,23 +1,25 @@
 /// Things about downloading.
 
-use std::{io::Write, marker::PhantomData, ops::Add, os::unix::fs::MetadataExt, sync::Arc, time::Duration};
+use std::{collections::HashMap, fmt::Display, io::Write, marker::PhantomData, ops::Add, os::unix::fs::MetadataExt, path::Path, sync::Arc, time::Duration};
 
 use anyhow::Result;
-use async_fetcher::{FetchEvent, Fetcher, Source};
+use async_fetcher::{Fetcher, Source};
+use async_shutdown::ShutdownManager;
 use futures_util::{io::AllowStdIo, StreamExt};
 
-use reqwest::IntoUrl;
-use sha1::{digest::{generic_array::ArrayLength, OutputSizeUser}, Digest, Sha1};
+use reqwest::{Method, RequestBuilder, Response, StatusCode};
+use sha1::{digest::{generic_array::ArrayLength, OutputSizeUser}, Digest};
 use tokio::{fs::{self, File}, io::{AsyncWrite, AsyncWriteExt}, sync::mpsc};
 use tokio_util::compat::TokioAsyncReadCompatExt;
 
-use crate::minecraft::schemas::Resource;
-use super::BetterPath;
+use crate::{minecraft::schemas::Resource, LauncherContext};
+use super::{progress::{ProgressTracker, PROGRESS_INTERVAL}, check_res, verify_downloaded, BetterPath, DownloadControl, DownloadEvent, DownloadPhase, MirrorProvider, ProgressSink};
 
 /// Check the hash of a file.
+/// Use [HashAlgorithm::check](super::HashAlgorithm::check) or [check_res] if the algorithm isn't known at compile time.
 /// 
 /// # Arguments
-/// * `T` - A hash algorithm like [Sha1] or [Sha256](sha2::Sha256)
+/// * `T` - A hash algorithm like [Sha1](sha1::Sha1) or [Sha256](sha2::Sha256)
 pub async fn check_hash<T: Digest + Write>(path: &BetterPath, digest: &str, size: usize, _: PhantomData<T>) -> bool
     where <T as OutputSizeUser>::OutputSize: Add, <<T as OutputSizeUser>::OutputSize as Add>::Output: ArrayLength<u8> {
     let meta = fs::metadata(path).await;
@@ -34,121 +36,303 @@ pub async fn check_hash<T: Digest + Write>(path: &BetterPath, digest: &str, size
             return false;
         }
         let out = sha1.into_inner().finalize();
-        format!("{out:x}") == digest
+        format!("{out:x}").eq_ignore_ascii_case(digest)
     } else {
         false
     }
 }
 
-/// Download a [Resource] to the `path`.
-pub async fn download_res(res: &Resource, path: &BetterPath) -> Result<()> {
-    if check_hash(path, &res.sha1, res.size, PhantomData::<Sha1>).await {
-        return Ok(());
-    }
-    download(res.url.clone(), path).await
-}
-
-/// Messages for download_all in channel.
-pub type DownloadAllMessage = std::result::Result<(BetterPath, FetchEvent), (BetterPath, anyhow::Error)>;
-
-async fn check_and_download(path: &BetterPath, res: &Resource, urls: Arc<[Box<str>]>) -> Option<(Source, Arc<()>)> {
-    if !check_hash(path, &res.sha1, res.size, PhantomData::<Sha1>).await {
+async fn check_and_download(path: &BetterPath, res: &Resource, urls: Arc<[Box<str>]>) -> Option<(Source, u64)> {
+    if !check_res(res, path).await {
         let _ = fs::create_dir_all(&path.0.parent().unwrap()).await;
         Some((Source {
             dest: Arc::from(path.0.as_path()),
             urls,
             part: None
-        }, Arc::new(())))
+        }, res.size as u64))
     } else {
         None
     }
 }
 
-/// Download [Resource]s to paths.
-pub async fn download_all(
-    resources: &Vec<(Resource, BetterPath)>, channel: mpsc::UnboundedSender<DownloadAllMessage>,
-    threads_per_file: u16, parallel_files: usize, retries: usize,
-    mirror: Option<String>
-) -> Result<()> {
-    let mut check_futures = vec![];
-    for (res, path) in resources {
-        let urls: Arc<[Box<str>]>;
-        if let Some(mirror) = &mirror {
-            urls = Arc::new([Box::from(mirrored(res.url.clone(), mirror.clone()).as_str()), Box::from(res.url.as_str())]);
+/// A file that failed to download.
+#[derive(Debug, Clone)]
+pub struct DownloadFailure {
+    /// Where the file should be.
+    pub path: BetterPath,
+    /// The URLs that are tried, including mirrors.
+    pub urls: Vec<String>,
+    /// The error of the last try.
+    pub error: String
+}
+
+/// The result of [download_all].
+#[derive(Debug, Clone, Default)]
+pub struct DownloadReport {
+    /// Files that are downloaded.
+    pub downloaded: Vec<BetterPath>,
+    /// Files that failed after all retries.
+    pub failed: Vec<DownloadFailure>,
+    /// Files that aren't downloaded, because they already exist and are valid.
+    pub skipped: Vec<BetterPath>
+}
+
+impl DownloadReport {
+    /// Whether no file failed.
+    pub fn is_ok(&self) -> bool {
+        self.failed.is_empty()
+    }
+
+    /// Fail with a [DownloadError] if any file failed.
+    pub fn into_result(self) -> std::result::Result<Self, DownloadError> {
+        if self.is_ok() {
+            Ok(self)
         } else {
-            urls = Arc::new([Box::from(res.url.as_str())]);
+            Err(DownloadError {
+                failed: self.failed
+            })
         }
-        check_futures.push(check_and_download(path, res, urls));
     }
-    let sources: Vec<_> = futures_util::future::join_all(check_futures).await
-        .into_iter()
-        .filter(|v|v.is_some())
-        .map(|v|v.unwrap()).collect();
+}
+
+/// Some required files failed to download.
+/// Installing and launching fail with it, which can be got with [anyhow::Error::downcast_ref].
+#[derive(Debug, Clone)]
+pub struct DownloadError {
+    /// The failed files.
+    pub failed: Vec<DownloadFailure>
+}
+
+impl Display for DownloadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", t!("download.failed", count = self.failed.len()))?;
+        for failure in &self.failed {
+            write!(f, "\n{}: {}", failure.path.0.display(), failure.error)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for DownloadError {}
+
+/// Remove a file that isn't completely downloaded, and its parts from multiple connections.
+async fn remove_partial(path: &Path) {
+    let _ = fs::remove_file(path).await;
+    let (Some(dir), Some(name)) = (path.parent(), path.file_name().and_then(|v|v.to_str())) else {
+        return;
+    };
+    let Ok(mut entries) = fs::read_dir(dir).await else {
+        return;
+    };
+    while let Ok(Some(entry)) = entries.next_entry().await {
+        if entry.file_name().to_str().and_then(|v|v.strip_prefix(name)).is_some_and(|v|v.starts_with(".part")) {
+            let _ = fs::remove_file(entry.path()).await;
+        }
+    }
+}
+
+/// Download `sources` until they are finished or failed, or `control` pauses or cancels them.
+/// Downloaded files are checked with the strongest hash of their [Resource]s in `expected`.
+/// Returns the interrupted ones, whose files are removed.
+async fn fetch_round(
+    sources: Vec<Source>, expected: &HashMap<&Path, &Resource>, tracker: &ProgressTracker<'_>, control: Option<&DownloadControl>,
+    threads_per_file: u16, parallel_files: usize, retries: usize
+) -> Vec<Source> {
+    let shutdown = ShutdownManager::new();
+    let inputs: Vec<_> = sources.iter()
+        .map(|v|(Source { dest: v.dest.clone(), urls: v.urls.clone(), part: None }, Arc::new(())))
+        .collect();
     let (tx, mut rx) = mpsc::unbounded_channel();
     let mut fetcher = Fetcher::default()
         .events(tx)
         .retries(retries as u16)
         .timeout(Duration::from_secs(15))
         .connections_per_file(threads_per_file)
+        .shutdown(shutdown.clone())
         .build()
-        .stream_from(futures_util::stream::iter(sources), parallel_files * (threads_per_file as usize));
-    let channel2 = channel.clone();
+        .stream_from(futures_util::stream::iter(inputs), parallel_files * (threads_per_file as usize));
+    let shutdown_ref = &shutdown;
+    // The fetcher is moved, so the event channel is closed when it finishes.
     let fetch_task = async move {
         while let Some((path, _, result)) = fetcher.next().await {
-            if let Err(e) = result {
-                let _ = tokio::fs::remove_file(&path).await;
-                let _ = channel2.send(Err((BetterPath::from(path.to_path_buf()), e.into())));
+            let result = match (result, expected.get(&*path)) {
+                (Ok(()), Some(res)) => verify_downloaded(res, &BetterPath(path.to_path_buf())).await,
+                (result, _) => result.map_err(anyhow::Error::from)
+            };
+            match result {
+                Ok(()) => tracker.finish(&path),
+                Err(e) if matches!(e.downcast_ref(), Some(async_fetcher::Error::Canceled)) => (),
+                Err(e) => {
+                    remove_partial(&path).await;
+                    tracker.fail(&path, e);
+                }
+            }
+        }
+        // The stream ends as soon as the shutdown is triggered, but the fetches are still running.
+        if shutdown_ref.is_shutdown_triggered() {
+            shutdown_ref.wait_shutdown_complete().await;
+        }
+    };
+    let event_task = async move {
+        let mut interval = tokio::time::interval(PROGRESS_INTERVAL);
+        loop {
+            tokio::select! {
+                event = rx.recv() => match event {
+                    Some((path, _, event)) => tracker.handle(&path, event),
+                    None => break
+                },
+                _ = interval.tick() => tracker.report()
             }
         }
     };
-    let send_task = async move {
-        while let Some((path, _, event)) = rx.recv().await {
-            let _ = channel.send(Ok((BetterPath::from(path.to_path_buf()), event)));
+    let tasks = async {
+        tokio::join!(fetch_task, event_task);
+    };
+    tokio::pin!(tasks);
+    let interrupted = async {
+        match control {
+            Some(control) => control.wait_interrupted().await,
+            None => std::future::pending().await
         }
     };
-    tokio::join!(fetch_task, send_task);
-    Ok(())
+    tokio::select! {
+        _ = &mut tasks => (),
+        _ = interrupted => {
+            let _ = shutdown.trigger_shutdown(());
+            tasks.await;
+        }
+    }
+    let mut remaining = vec![];
+    for source in sources {
+        if tracker.interrupt(&source.dest) {
+            remove_partial(&source.dest).await;
+            remaining.push(source);
+        }
+    }
+    remaining
 }
 
-fn mirrored(url: String, mirror: String) -> String {
-    return url
-        .replace("resources.download.minecraft.net", &format!("{mirror}/assets"))
-        .replace("libraries.minecraft.net", &format!("{mirror}/maven"))
-        .replace("files.minecraftforge.net", &mirror)
-        .replace("maven.fabricmc.net", &mirror)
-        .replace("maven.neoforged.net/releases/net/neoforged/neoforge", &format!("{mirror}/maven/net/neoforged/neoforge"))
-        .replace("resources.download.minecraft.net", &format!("{mirror}/assets"));
+/// Download [Resource]s to paths, and report the progress to `progress` as the `phase`.
+/// It can be paused or cancelled with [ProgressSink::control]. Files that are already downloaded are kept after pausing.
+/// Downloaded files are checked with the strongest hash of their [Resource]s, and fail if it's wrong.
+/// It only fails if it's cancelled. Check [DownloadReport::failed], or use [DownloadReport::into_result].
+pub async fn download_all(
+    resources: &Vec<(Resource, BetterPath)>, phase: DownloadPhase, progress: &dyn ProgressSink,
+    threads_per_file: u16, parallel_files: usize, retries: usize,
+    mirror: &dyn MirrorProvider
+) -> Result<DownloadReport> {
+    let control = progress.control();
+    if let Some(control) = control {
+        control.wait_running().await?;
+    }
+    let mut check_futures = vec![];
+    for (res, path) in resources {
+        let urls: Arc<[Box<str>]> = mirror.get_urls(&res.url).into_iter().map(Box::from).collect();
+        check_futures.push(check_and_download(path, res, urls));
+    }
+    let mut report = DownloadReport::default();
+    let mut sources = vec![];
+    for ((_, path), source) in resources.iter().zip(futures_util::future::join_all(check_futures).await) {
+        match source {
+            Some(source) => sources.push(source),
+            None => report.skipped.push(path.clone())
+        }
+    }
+    let urls: HashMap<_, _> = sources.iter()
+        .map(|(source, _)|(source.dest.to_path_buf(), source.urls.iter().map(|v|v.to_string()).collect::<Vec<_>>()))
+        .collect();
+    let expected: HashMap<_, _> = resources.iter().map(|(res, path)|(path.0.as_path(), res)).collect();
+    let tracker = ProgressTracker::start(progress, phase, sources.iter().map(|(source, size)|(source.dest.to_path_buf(), *size)));
+    let mut sources: Vec<_> = sources.into_iter().map(|(source, _)|source).collect();
+    loop {
+        sources = fetch_round(sources, &expected, &tracker, control, threads_per_file, parallel_files, retries).await;
+        let Some(control) = control.filter(|_|!sources.is_empty()) else {
+            break;
+        };
+        if !control.is_cancelled() {
+            tracker.send(DownloadEvent::Paused);
+        }
+        if let Err(e) = control.wait_running().await {
+            tracker.send(DownloadEvent::Cancelled);
+            return Err(e);
+        }
+        tracker.send(DownloadEvent::Resumed);
+    }
+    let (downloaded, failed) = tracker.end();
+    report.downloaded = downloaded.into_iter().map(BetterPath).collect();
+    report.failed = failed.into_iter()
+        .map(|(path, error)|DownloadFailure {
+            urls: urls.get(&path).cloned().unwrap_or_default(),
+            path: BetterPath(path),
+            error
+        })
+        .collect();
+    Ok(report)
 }
 
-/// Read the `url` into the `writer`.
-pub async fn download_to_writer<URL: IntoUrl, W: AsyncWrite + std::marker::Unpin>(url: URL, writer: &mut W) -> Result<()> {
-    let mut stream = reqwest::get(url).await?.bytes_stream();
+impl LauncherContext {
+    /// Send a request to `url` and its mirrors from [Self::mirror_provider] in order, and return the first successful or `304 Not Modified` response.
+    /// Requests other than GET and HEAD may change something, like redeeming a refresh token,
+    /// so they're only sent to the next URL if connecting fails or the server responds with a 5xx error. Other responses are returned as they are.
+    /// The response from the last URL is returned even if it isn't successful.
+    pub(crate) async fn send(&self, method: Method, url: &str, build: impl Fn(RequestBuilder) -> RequestBuilder) -> reqwest::Result<Response> {
+        let idempotent = method == Method::GET || method == Method::HEAD;
+        let mut urls = self.mirror_provider.get_urls(url);
+        let last = urls.pop().unwrap_or(url.to_string());
+        for url in urls {
+            match build(self.http_client.request(method.clone(), url)).send().await {
+                Ok(res) if res.status().is_success() || res.status() == StatusCode::NOT_MODIFIED => return Ok(res),
+                Ok(res) if !idempotent && !res.status().is_server_error() => return Ok(res),
+                Err(e) if !idempotent && !e.is_connect() => return Err(e),
+                _ => ()
+            }
+        }
+        build(self.http_client.request(method, last)).send().await
+    }
 
-    while let Some(chunk) = stream.next().await {
-        writer.write_all(&chunk?).await?;
-        writer.flush().await?
+    /// Send a GET request to `url` with [Self::send].
+    pub(crate) async fn get(&self, url: &str) -> reqwest::Result<Response> {
+        self.send(Method::GET, url, |v|v).await
     }
 
-    writer.flush().await?;
-    Ok(())
-}
+    /// Read the `url` into the `writer`, using [Self::mirror_provider].
+    pub async fn download_to_writer<W: AsyncWrite + std::marker::Unpin>(&self, url: &str, writer: &mut W) -> Result<()> {
+        let mut stream = self.get(url).await?.error_for_status()?.bytes_stream();
+
+        while let Some(chunk) = stream.next().await {
+            writer.write_all(&chunk?).await?;
+        }
 
-/// Download the `url` into the `path`.
-pub async fn download<URL: IntoUrl>(url: URL, path: &BetterPath) -> Result<()> {
-    if let Some(p) = path.0.parent() {
-        fs::create_dir_all(p).await?;
+        writer.flush().await?;
+        Ok(())
     }
-    let mut file = File::create(path).await?;
 
-    download_to_writer(url, &mut file).await
-}
+    /// Download the `url` into the `path`, using [Self::mirror_provider].
+    pub async fn download(&self, url: &str, path: &BetterPath) -> Result<()> {
+        if let Some(p) = path.0.parent() {
+            fs::create_dir_all(p).await?;
+        }
+        let mut file = File::create(path).await?;
+
+        self.download_to_writer(url, &mut file).await
+    }
+
+    /// Download the `url` into the `path`, and return the content, using [Self::mirror_provider].
+    pub async fn download_txt(&self, url: &str, path: &BetterPath) -> Result<String> {
+        let txt = self.get(url).await?.error_for_status()?.text().await?;
+        if let Some(p) = path.0.parent() {
+            fs::create_dir_all(p).await?;
+        }
+        fs::write(path, &txt).await?;
+        Ok(txt)
+    }
 
-/// Download the `url` into the `path`, and return the content.
-pub async fn download_txt<'o, URL: IntoUrl>(url: URL, path: &BetterPath) -> Result<String> {
-    let txt = reqwest::get(url).await?.text().await?;
-    if let Some(p) = path.0.parent() {
-        fs::create_dir_all(p).await?;
+    /// Download a [Resource] to the `path` if its hash is wrong, and check it with the strongest hash, using [Self::mirror_provider].
+    pub async fn download_res(&self, res: &Resource, path: &BetterPath) -> Result<()> {
+        if check_res(res, path).await {
+            return Ok(());
+        }
+        self.download(&res.url, path).await?;
+        verify_downloaded(res, path).await
     }
-    fs::write(path, &txt).await?;
-    Ok(txt)
-}
\ No newline at end of file
+}
@@ -0,0 +1,75 @@
+//! Things about checking files with hashes.
+
+use std::{fmt::Display, marker::PhantomData};
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use sha1::Sha1;
+use sha2::{Sha256, Sha512};
+use tokio::fs;
+
+use crate::minecraft::schemas::Resource;
+
+use super::{check_hash, BetterPath};
+
+/// A hash algorithm that files can be checked with.
+/// Stronger algorithms are greater, and the strongest known one of a [Resource] is used.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum HashAlgorithm {
+    /// SHA-1, used by Mojang and most mod loaders.
+    Sha1,
+    /// SHA-256, used by authlib-injector.
+    Sha256,
+    /// SHA-512, used by Modrinth.
+    Sha512
+}
+
+impl Display for HashAlgorithm {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            HashAlgorithm::Sha1 => "SHA-1",
+            HashAlgorithm::Sha256 => "SHA-256",
+            HashAlgorithm::Sha512 => "SHA-512"
+        })
+    }
+}
+
+impl HashAlgorithm {
+    /// Whether `digest` is a hex digest of this algorithm, instead of empty or a placeholder.
+    pub fn is_digest(self, digest: &str) -> bool {
+        let len = match self {
+            HashAlgorithm::Sha1 => 40,
+            HashAlgorithm::Sha256 => 64,
+            HashAlgorithm::Sha512 => 128
+        };
+        digest.len() == len && digest.chars().all(|c|c.is_ascii_hexdigit())
+    }
+
+    /// Check the hash of a file with [check_hash]. `size` is ignored if it's 0.
+    pub async fn check(self, path: &BetterPath, digest: &str, size: usize) -> bool {
+        match self {
+            HashAlgorithm::Sha1 => check_hash(path, digest, size, PhantomData::<Sha1>).await,
+            HashAlgorithm::Sha256 => check_hash(path, digest, size, PhantomData::<Sha256>).await,
+            HashAlgorithm::Sha512 => check_hash(path, digest, size, PhantomData::<Sha512>).await
+        }
+    }
+}
+
+/// Check a file with the strongest hash of `res`.
+/// Returns false if `res` has no hash, so the file is always downloaded.
+pub async fn check_res(res: &Resource, path: &BetterPath) -> bool {
+    match res.strongest_hash() {
+        Some((algorithm, digest)) => algorithm.check(path, digest, res.size).await,
+        None => false
+    }
+}
+
+/// Check a downloaded file with the strongest hash of `res`, and remove it if it's wrong. Files without hashes pass.
+pub(crate) async fn verify_downloaded(res: &Resource, path: &BetterPath) -> Result<()> {
+    if let Some((algorithm, digest)) = res.strongest_hash() && !algorithm.check(path, digest, res.size).await {
+        let _ = fs::remove_file(path).await;
+        return Err(anyhow!(t!("download.hash_mismatch", algorithm = algorithm)));
+    }
+    Ok(())
+}
@@ -0,0 +1,74 @@
+//! Things about download mirrors.
+
+/// Maps URLs to mirrors. Set it to [crate::LauncherContext::mirror_provider].
+pub trait MirrorProvider: Send + Sync {
+    /// Get the URLs that should be tried in order for `url`.
+    /// Usually `url` itself should be the last one, as a fallback.
+    fn get_urls(&self, url: &str) -> Vec<String>;
+}
+
+/// Always uses the original URLs.
+pub struct IdentityMirrorProvider;
+
+impl MirrorProvider for IdentityMirrorProvider {
+    fn get_urls(&self, url: &str) -> Vec<String> {
+        vec![url.to_string()]
+    }
+}
+
+/// Hosts and path prefixes that BMCLAPI mirrors, and their paths on BMCLAPI.
+/// Longer prefixes are before shorter ones of the same host.
+const BMCLAPI_RULES: [(&str, &str); 14] = [
+    ("launchermeta.mojang.com", ""),
+    ("launcher.mojang.com", ""),
+    ("piston-meta.mojang.com", ""),
+    ("piston-data.mojang.com", ""),
+    ("resources.download.minecraft.net", "/assets"),
+    ("libraries.minecraft.net", "/maven"),
+    ("files.minecraftforge.net/maven", "/maven"),
+    ("files.minecraftforge.net", ""),
+    ("maven.minecraftforge.net", "/maven"),
+    ("maven.neoforged.net/releases", "/maven"),
+    ("maven.fabricmc.net", "/maven"),
+    ("meta.fabricmc.net", "/fabric-meta"),
+    ("authlib-injector.yushi.moe", "/mirrors/authlib-injector"),
+    ("bmclapi2.bangbang93.com", "")
+];
+
+/// Uses BMCLAPI (<https://bmclapi2.bangbang93.com>) or its mirrors, and falls back to the original URLs.
+pub struct BMCLAPIMirrorProvider {
+    /// The host, like `bmclapi2.bangbang93.com`.
+    pub host: String
+}
+
+impl BMCLAPIMirrorProvider {
+    /// Use BMCLAPI on `host`.
+    pub fn new(host: &str) -> Self {
+        Self {
+            host: host.to_string()
+        }
+    }
+
+    /// Use the official BMCLAPI.
+    pub fn official() -> Self {
+        Self::new("bmclapi2.bangbang93.com")
+    }
+}
+
+impl MirrorProvider for BMCLAPIMirrorProvider {
+    fn get_urls(&self, url: &str) -> Vec<String> {
+        let Some((_, rest)) = url.split_once("://") else {
+            return vec![url.to_string()];
+        };
+        for (from, to) in BMCLAPI_RULES {
+            if let Some(path) = rest.strip_prefix(from) && (path.is_empty() || path.starts_with('/')) {
+                let mirrored = format!("https://{}{to}{path}", self.host);
+                if mirrored == url {
+                    break;
+                }
+                return vec![mirrored, url.to_string()];
+            }
+        }
+        vec![url.to_string()]
+    }
+}
@@ -0,0 +1,407 @@
+//! Things about reporting download progress, and pausing or cancelling downloads.
+
+use std::{collections::HashMap, future::Future, path::{Path, PathBuf}, sync::{Arc, Mutex}, time::{Duration, Instant}};
+
+use anyhow::{anyhow, Result};
+use async_fetcher::FetchEvent;
+use tokio::sync::{mpsc, watch};
+
+use super::BetterPath;
+
+/// Progress of a whole download task, like downloading all files of an installation.
+/// Files that are already downloaded aren't counted.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DownloadProgress {
+    /// The number of files to download.
+    pub total_files: usize,
+    /// The number of downloaded files.
+    pub finished_files: usize,
+    /// The number of files that failed.
+    pub failed_files: usize,
+    /// The total size in bytes. Files whose size is unknown are added when their size is known.
+    pub total_bytes: u64,
+    /// Downloaded bytes.
+    pub downloaded_bytes: u64,
+    /// Current speed in bytes per second.
+    pub speed: u64,
+    /// Estimated remaining time. It's [None] if the speed is 0.
+    pub eta: Option<Duration>
+}
+
+/// What a download task downloads.
+/// An operation like installing a version or a mod loader runs several tasks, one per phase.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DownloadPhase {
+    /// The assets index of a version.
+    AssetsIndex,
+    /// Libraries, assets, the client jar and logging configs of a version.
+    GameFiles,
+    /// A Java runtime provided by Mojang.
+    JavaRuntime,
+    /// The installer of a mod loader.
+    LoaderInstaller,
+    /// Libraries of a mod loader.
+    LoaderLibraries,
+    /// The server jar.
+    Server,
+    /// Files from content services, like mods.
+    Content,
+    /// Broken files found by verifying.
+    Repair
+}
+
+/// Events of a download task. Send to a [ProgressSink].
+/// An operation may run several tasks one after another, and each [Self::Started] begins a new phase with its own totals.
+#[derive(Debug)]
+pub enum DownloadEvent {
+    /// The task of a phase starts, and the totals are known.
+    Started(DownloadPhase, DownloadProgress),
+    /// The task progresses. It's sent periodically.
+    Progress(DownloadProgress),
+    /// All files are downloaded or failed.
+    Finished(DownloadProgress),
+    /// The task is paused with [DownloadControl::pause]. Unfinished files are removed, and downloaded again after resuming.
+    Paused(DownloadProgress),
+    /// The task is resumed with [DownloadControl::resume].
+    Resumed(DownloadProgress),
+    /// The task is cancelled with [DownloadControl::cancel]. Unfinished files are removed.
+    Cancelled(DownloadProgress),
+    /// A file starts downloading.
+    FileStarted(BetterPath),
+    /// A file is downloaded.
+    FileFinished(BetterPath),
+    /// A file is downloaded again after an error.
+    FileRetrying(BetterPath),
+    /// A file failed after all retries.
+    FileFailed(BetterPath, anyhow::Error)
+}
+
+/// Receives [DownloadEvent]s. It should be implemented by the client, for example to update a progress bar.
+/// Use a [mpsc::UnboundedSender] to receive events in another task, or [NoProgress] to ignore them.
+/// Wrap it with [DownloadControl::with] to pause or cancel the operation.
+pub trait ProgressSink: Send + Sync {
+    /// Handle an event. It shouldn't block.
+    fn on_event(&self, event: DownloadEvent);
+
+    /// The [DownloadControl] of the operation. The operation can't be paused or cancelled if it's [None].
+    fn control(&self) -> Option<&DownloadControl> {
+        None
+    }
+}
+
+impl ProgressSink for mpsc::UnboundedSender<DownloadEvent> {
+    fn on_event(&self, event: DownloadEvent) {
+        let _ = self.send(event);
+    }
+}
+
+/// A [ProgressSink] that ignores all events.
+pub struct NoProgress;
+
+impl ProgressSink for NoProgress {
+    fn on_event(&self, _: DownloadEvent) {}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ControlState {
+    Running,
+    Paused,
+    Cancelled
+}
+
+/// Pauses or cancels long-running operations, like installing and launching, from another task.
+/// Clones control the same operations.
+#[derive(Debug, Clone)]
+pub struct DownloadControl {
+    state: Arc<watch::Sender<ControlState>>
+}
+
+impl Default for DownloadControl {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DownloadControl {
+    /// Create a running [DownloadControl].
+    pub fn new() -> Self {
+        Self {
+            state: Arc::new(watch::Sender::new(ControlState::Running))
+        }
+    }
+
+    /// Wrap `sink`, so operations reporting to it are controlled by this.
+    pub fn with<S: ProgressSink>(&self, sink: S) -> Controlled<S> {
+        Controlled {
+            sink,
+            control: self.clone()
+        }
+    }
+
+    /// Cancel the operations. It can't be undone.
+    /// They fail soon, and files that aren't completely downloaded are removed.
+    pub fn cancel(&self) {
+        self.state.send_replace(ControlState::Cancelled);
+    }
+
+    /// Pause the operations. Downloaded files are kept, and the others are removed and downloaded again after [Self::resume].
+    pub fn pause(&self) {
+        self.state.send_if_modified(|v|*v == ControlState::Running && {
+            *v = ControlState::Paused;
+            true
+        });
+    }
+
+    /// Resume the paused operations.
+    pub fn resume(&self) {
+        self.state.send_if_modified(|v|*v == ControlState::Paused && {
+            *v = ControlState::Running;
+            true
+        });
+    }
+
+    /// Whether it's cancelled.
+    pub fn is_cancelled(&self) -> bool {
+        *self.state.borrow() == ControlState::Cancelled
+    }
+
+    /// Whether it's paused.
+    pub fn is_paused(&self) -> bool {
+        *self.state.borrow() == ControlState::Paused
+    }
+
+    /// Fail if it's cancelled.
+    pub(crate) fn check(&self) -> Result<()> {
+        if self.is_cancelled() {
+            return Err(anyhow!(t!("download.cancelled")));
+        }
+        Ok(())
+    }
+
+    /// Wait until it isn't paused, and fail if it's cancelled.
+    pub(crate) async fn wait_running(&self) -> Result<()> {
+        let mut rx = self.state.subscribe();
+        let _ = rx.wait_for(|v|*v != ControlState::Paused).await;
+        self.check()
+    }
+
+    /// Wait until it's paused or cancelled.
+    pub(crate) async fn wait_interrupted(&self) {
+        let mut rx = self.state.subscribe();
+        let _ = rx.wait_for(|v|*v != ControlState::Running).await;
+    }
+
+    /// Wait until it's cancelled.
+    pub(crate) async fn wait_cancelled(&self) {
+        let mut rx = self.state.subscribe();
+        let _ = rx.wait_for(|v|*v == ControlState::Cancelled).await;
+    }
+}
+
+/// A [ProgressSink] controlled by a [DownloadControl]. See [DownloadControl::with].
+pub struct Controlled<S> {
+    sink: S,
+    control: DownloadControl
+}
+
+impl<S: ProgressSink> ProgressSink for Controlled<S> {
+    fn on_event(&self, event: DownloadEvent) {
+        self.sink.on_event(event);
+    }
+
+    fn control(&self) -> Option<&DownloadControl> {
+        Some(&self.control)
+    }
+}
+
+/// Fail if the operation reporting to `progress` is cancelled.
+pub(crate) fn check_cancelled(progress: &dyn ProgressSink) -> Result<()> {
+    progress.control().map_or(Ok(()), DownloadControl::check)
+}
+
+/// Run `future`, and stop it if the operation reporting to `progress` is cancelled.
+/// It isn't affected by pausing, so it should be something that can't be resumed, like a single request.
+pub(crate) async fn cancellable<T>(progress: &dyn ProgressSink, future: impl Future<Output = Result<T>>) -> Result<T> {
+    let Some(control) = progress.control() else {
+        return future.await;
+    };
+    tokio::select! {
+        result = future => result,
+        _ = control.wait_cancelled() => Err(anyhow!(t!("download.cancelled")))
+    }
+}
+
+/// Wait for `child`, and kill it if the operation reporting to `progress` is cancelled.
+#[cfg(feature="components_installation")]
+pub(crate) async fn wait_child(progress: &dyn ProgressSink, child: &mut tokio::process::Child) -> Result<std::process::ExitStatus> {
+    let result = cancellable(progress, async { Ok(child.wait().await?) }).await;
+    if result.is_err() {
+        let _ = child.kill().await;
+    }
+    result
+}
+
+/// How often [DownloadEvent::Progress] is sent.
+pub(crate) const PROGRESS_INTERVAL: Duration = Duration::from_millis(500);
+
+struct FileProgress {
+    size: u64,
+    downloaded: u64,
+    done: bool
+}
+
+struct TrackerState {
+    progress: DownloadProgress,
+    files: HashMap<PathBuf, FileProgress>,
+    finished: Vec<PathBuf>,
+    failed: Vec<(PathBuf, String)>,
+    window_start: Instant,
+    window_bytes: u64
+}
+
+/// Turns events of [async_fetcher] into [DownloadEvent]s for a task.
+pub(crate) struct ProgressTracker<'a> {
+    sink: &'a dyn ProgressSink,
+    state: Mutex<TrackerState>
+}
+
+impl<'a> ProgressTracker<'a> {
+    /// Start tracking `files` of the `phase`, which are paths with sizes. 0 means unknown.
+    pub(crate) fn start(sink: &'a dyn ProgressSink, phase: DownloadPhase, files: impl Iterator<Item = (PathBuf, u64)>) -> Self {
+        let mut progress = DownloadProgress::default();
+        let files: HashMap<_, _> = files.map(|(path, size)| {
+            progress.total_bytes += size;
+            (path, FileProgress { size, downloaded: 0, done: false })
+        }).collect();
+        progress.total_files = files.len();
+        sink.on_event(DownloadEvent::Started(phase, progress));
+        Self {
+            sink,
+            state: Mutex::new(TrackerState {
+                progress,
+                files,
+                finished: vec![],
+                failed: vec![],
+                window_start: Instant::now(),
+                window_bytes: 0
+            })
+        }
+    }
+
+    fn add_bytes(state: &mut TrackerState, path: &Path, bytes: u64) {
+        if let Some(file) = state.files.get_mut(path) && !file.done {
+            file.downloaded += bytes;
+            state.progress.downloaded_bytes += bytes;
+            state.window_bytes += bytes;
+        }
+    }
+
+    /// Handle an event from [async_fetcher].
+    pub(crate) fn handle(&self, path: &Path, event: FetchEvent) {
+        let mut state = self.state.lock().unwrap();
+        let event = match event {
+            FetchEvent::Fetching => Some(DownloadEvent::FileStarted(BetterPath(path.to_path_buf()))),
+            FetchEvent::ContentLength(length) => {
+                if let Some(file) = state.files.get_mut(path) && file.size == 0 {
+                    file.size = length;
+                    state.progress.total_bytes += length;
+                }
+                None
+            },
+            FetchEvent::Progress(bytes) => {
+                Self::add_bytes(&mut state, path, bytes);
+                None
+            },
+            FetchEvent::Retrying => {
+                // Bytes of the failed attempt are downloaded again.
+                if let Some(file) = state.files.get_mut(path) && !file.done {
+                    let downloaded = std::mem::take(&mut file.downloaded);
+                    state.progress.downloaded_bytes -= downloaded;
+                }
+                Some(DownloadEvent::FileRetrying(BetterPath(path.to_path_buf())))
+            },
+            // The result from the fetcher is used instead.
+            FetchEvent::Fetched => None
+        };
+        drop(state);
+        if let Some(event) = event {
+            self.sink.on_event(event);
+        }
+    }
+
+    /// A file is downloaded.
+    pub(crate) fn finish(&self, path: &Path) {
+        let mut state = self.state.lock().unwrap();
+        if let Some(file) = state.files.get(path) {
+            // The last chunk isn't always reported.
+            let rest = file.size.saturating_sub(file.downloaded);
+            Self::add_bytes(&mut state, path, rest);
+        }
+        if let Some(file) = state.files.get_mut(path) {
+            file.done = true;
+        }
+        state.progress.finished_files += 1;
+        state.finished.push(path.to_path_buf());
+        drop(state);
+        self.sink.on_event(DownloadEvent::FileFinished(BetterPath(path.to_path_buf())));
+    }
+
+    /// A file failed.
+    pub(crate) fn fail(&self, path: &Path, error: anyhow::Error) {
+        let mut state = self.state.lock().unwrap();
+        if let Some(file) = state.files.get_mut(path) {
+            file.done = true;
+        }
+        state.progress.failed_files += 1;
+        state.failed.push((path.to_path_buf(), format!("{error:#}")));
+        drop(state);
+        self.sink.on_event(DownloadEvent::FileFailed(BetterPath(path.to_path_buf()), error));
+    }
+
+    /// A file is interrupted by pausing or cancelling, and isn't downloaded.
+    /// Returns false if it's already finished or failed.
+    pub(crate) fn interrupt(&self, path: &Path) -> bool {
+        let mut state = self.state.lock().unwrap();
+        let Some(file) = state.files.get_mut(path) else {
+            return false;
+        };
+        if file.done {
+            return false;
+        }
+        let downloaded = std::mem::take(&mut file.downloaded);
+        state.progress.downloaded_bytes -= downloaded;
+        true
+    }
+
+    /// Send an event with current progress, like [DownloadEvent::Paused].
+    pub(crate) fn send(&self, event: fn(DownloadProgress) -> DownloadEvent) {
+        let mut progress = self.state.lock().unwrap().progress;
+        progress.speed = 0;
+        progress.eta = None;
+        self.sink.on_event(event(progress));
+    }
+
+    /// Update the speed, and send [DownloadEvent::Progress].
+    pub(crate) fn report(&self) {
+        let mut state = self.state.lock().unwrap();
+        let elapsed = state.window_start.elapsed();
+        if !elapsed.is_zero() {
+            state.progress.speed = (state.window_bytes as f64 / elapsed.as_secs_f64()) as u64;
+        }
+        state.window_start = Instant::now();
+        state.window_bytes = 0;
+        let progress = &mut state.progress;
+        let remaining = progress.total_bytes.saturating_sub(progress.downloaded_bytes);
+        progress.eta = (progress.speed != 0).then(||Duration::from_secs(remaining / progress.speed));
+        let progress = *progress;
+        drop(state);
+        self.sink.on_event(DownloadEvent::Progress(progress));
+    }
+
+    /// Send [DownloadEvent::Finished], and return the finished files and the failed files with errors.
+    pub(crate) fn end(self) -> (Vec<PathBuf>, Vec<(PathBuf, String)>) {
+        let state = self.state.into_inner().unwrap();
+        self.sink.on_event(DownloadEvent::Finished(state.progress));
+        (state.finished, state.failed)
+    }
+}